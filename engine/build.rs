@@ -1,6 +1,94 @@
 use cfg_aliases::cfg_aliases;
+use std::{
+    path::Path,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// Runs a command and returns its trimmed stdout, or None if it couldn't be
+// run or failed
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+#[path = "src/util/date.rs"]
+mod date;
+
+// Formats seconds since the epoch as a UTC date (YYYY-MM-DD)
+fn format_date(secs: u64) -> String {
+    let (year, month, day) = date::civil_from_days(secs / 86400);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+// Passes information about the build to the engine as environment variables,
+// to be read by com::get_build_* with env!()
+fn emit_build_info() {
+    let git_rev = command_output("git", &["rev-parse", "--short=12", "HEAD"])
+        .unwrap_or_else(|| String::from("unknown"));
+    let git_dirty = command_output("git", &["status", "--porcelain"])
+        .map_or(false, |status| !status.is_empty());
+
+    // Honor SOURCE_DATE_EPOCH so that builds can be reproducible
+    let secs = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs())
+        });
+
+    let profile =
+        std::env::var("PROFILE").unwrap_or_else(|_| String::from("unknown"));
+
+    // Optional dependencies (e.g. ash) show up as features too, but only the
+    // <os>_use_<backend> features say anything about the build
+    let mut features = std::env::vars()
+        .filter_map(|(k, _)| {
+            k.strip_prefix("CARGO_FEATURE_").map(str::to_lowercase)
+        })
+        .filter(|feature| feature.contains("_use_"))
+        .collect::<Vec<_>>();
+    features.sort();
+
+    let rustc =
+        std::env::var("RUSTC").unwrap_or_else(|_| String::from("rustc"));
+    let rustc_version = command_output(&rustc, &["--version"])
+        .unwrap_or_else(|| String::from("unknown"));
+
+    println!("cargo:rustc-env=OPEN_T5_GIT_REV={git_rev}");
+    println!("cargo:rustc-env=OPEN_T5_GIT_DIRTY={git_dirty}");
+    println!("cargo:rustc-env=OPEN_T5_BUILD_DATE={}", format_date(secs));
+    println!("cargo:rustc-env=OPEN_T5_BUILD_PROFILE={profile}");
+    println!(
+        "cargo:rustc-env=OPEN_T5_BUILD_FEATURES={}",
+        features.join(" ")
+    );
+    println!("cargo:rustc-env=OPEN_T5_RUSTC_VERSION={rustc_version}");
+
+    // Rerun whenever the sources or the checked-out commit change, so the
+    // revision and dirty state don't go stale
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=Cargo.toml");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    if let Some(git_dir) =
+        command_output("git", &["rev-parse", "--absolute-git-dir"])
+    {
+        for file in ["HEAD", "index"] {
+            let path = Path::new(&git_dir).join(file);
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+    }
+}
 
 fn main() {
+    emit_build_info();
+
     // Setup cfg aliases
     cfg_aliases! {
         // OSes
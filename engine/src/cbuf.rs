@@ -1,10 +1,283 @@
 #![allow(dead_code)]
 
-#[allow(clippy::print_stdout)]
-pub fn add_text(i: i32, text: &str) {
-    println!("cbuf::add_text: ({}) {}", i, text);
+// This file implements the command buffer. Text is queued per local client
+// with `add_text`, and then split into individual commands and executed by
+// `execute`, which is called once per frame from `com::frame`.
+//
+// A `wait` stops execution for the rest of the frame (or for several
+// frames). Text being executed immediately by `execute_buffer` (e.g. a
+// config file) isn't in the command buffer, so when it waits, whatever is
+// left of it is moved to the front of the command buffer to be resumed
+// along with everything else.
+
+use crate::*;
+
+use core::cell::RefCell;
+use lazy_static::lazy_static;
+use std::sync::RwLock;
+
+/// Maximum number of local clients that get their own command buffer.
+pub const MAX_LOCAL_CLIENTS: usize = 4;
+
+/// Maximum number of bytes that can be queued in a single command buffer.
+const MAX_CMD_BUFFER: usize = 0x0001_0000;
+
+lazy_static! {
+    static ref CMD_TEXT: RwLock<[String; MAX_LOCAL_CLIENTS]> =
+        RwLock::new(Default::default());
+    static ref WAIT_FRAMES: RwLock<[usize; MAX_LOCAL_CLIENTS]> =
+        RwLock::new(Default::default());
+}
+
+thread_local! {
+    // Text of each (nested) call to execute_buffer that's currently running,
+    // innermost last
+    static BUFFER_STACK: RefCell<Vec<String>> = RefCell::new(Vec::new());
+    // Leftover text from execute_buffer calls that were interrupted by a
+    // wait, innermost first, to be moved into CMD_TEXT once the outermost
+    // call returns
+    static DEFERRED_TEXT: RefCell<String> = RefCell::new(String::new());
+}
+
+/// Converts a local client number into an index into [`CMD_TEXT`].
+///
+/// Returns [`None`] (and prints an error) if the number is out of range.
+fn buffer_index(local_client_num: i32) -> Option<usize> {
+    match usize::try_from(local_client_num) {
+        Ok(i) if i < MAX_LOCAL_CLIENTS => Some(i),
+        _ => {
+            com::print_errorln!(
+                console::Channel::ERROR,
+                "cbuf: invalid local client number {}",
+                local_client_num
+            );
+            None
+        }
+    }
+}
+
+/// Appends `text` to the end of the command buffer for `local_client_num`.
+///
+/// Nothing is executed until the next call to [`execute`]. If the buffer
+/// doesn't have enough room left for `text`, the text is discarded.
+pub fn add_text(local_client_num: i32, text: &str) {
+    let Some(i) = buffer_index(local_client_num) else {
+        return;
+    };
+
+    let mut cmd_text = CMD_TEXT.write().unwrap();
+    let buf = cmd_text.get_mut(i).unwrap();
+    if buf.len() + text.len() > MAX_CMD_BUFFER {
+        com::print_errorln!(
+            console::Channel::ERROR,
+            "cbuf::add_text: overflow"
+        );
+        return;
+    }
+
+    buf.push_str(text);
+}
+
+/// Appends `text` with a newline to the command buffer for
+/// `local_client_num`.
+pub fn add_textln(local_client_num: i32, text: &str) {
+    add_text(local_client_num, &format!("{}\n", text));
+}
+
+/// Inserts `text` with a newline before everything else waiting to be
+/// executed for `local_client_num`, so that it's the next thing executed.
+///
+/// If called while [`execute_buffer`] is running, the text is inserted into
+/// that buffer instead of the command buffer.
+pub fn insert_text(local_client_num: i32, text: &str) {
+    let text = format!("{}\n", text);
+    let inserted = BUFFER_STACK.with(|stack| {
+        stack
+            .borrow_mut()
+            .last_mut()
+            .map(|buf| buf.insert_str(0, &text))
+            .is_some()
+    });
+    if inserted {
+        return;
+    }
+
+    let Some(i) = buffer_index(local_client_num) else {
+        return;
+    };
+
+    let mut cmd_text = CMD_TEXT.write().unwrap();
+    let buf = cmd_text.get_mut(i).unwrap();
+    if buf.len() + text.len() > MAX_CMD_BUFFER {
+        com::print_errorln!(
+            console::Channel::ERROR,
+            "cbuf::insert_text: overflow"
+        );
+        return;
+    }
+
+    buf.insert_str(0, &text);
+}
+
+/// Stops executing commands for `local_client_num` until `frames` frames
+/// from now. The rest of the commands are left where they are, and resume
+/// from the same point.
+pub fn wait(local_client_num: i32, frames: usize) {
+    let Some(i) = buffer_index(local_client_num) else {
+        return;
+    };
+
+    *WAIT_FRAMES.write().unwrap().get_mut(i).unwrap() = frames;
+}
+
+fn is_waiting(i: usize) -> bool {
+    *WAIT_FRAMES.read().unwrap().get(i).unwrap() > 0
+}
+
+/// Finds the end of the first command in `text`.
+///
+/// Commands are terminated by a newline, or by a semicolon that isn't inside
+/// of quotes or a `//` comment. Inside of quotes, `\"` is an escaped quote
+/// (as it is for [`cmd::tokenize_string`]) and doesn't end them. The
+/// returned index is that of the terminator, or `text.len()` if there isn't
+/// one.
+fn command_end(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut in_quotes = false;
+    let mut in_comment = false;
+    let mut escaped = false;
+
+    for (i, &c) in bytes.iter().enumerate() {
+        if escaped {
+            escaped = false;
+            if c == b'"' {
+                continue;
+            }
+        }
+
+        match c {
+            b'\n' | b'\r' => return i,
+            _ if in_comment => {}
+            b'\\' if in_quotes => escaped = true,
+            b'"' => in_quotes = !in_quotes,
+            b'/' if !in_quotes && bytes.get(i + 1) == Some(&b'/') => {
+                in_comment = true;
+            }
+            b';' if !in_quotes => return i,
+            _ => {}
+        }
+    }
+
+    bytes.len()
+}
+
+/// Removes the first command from `buf` and returns it, or returns [`None`]
+/// if `buf` is empty.
+fn split_command(buf: &mut String) -> Option<String> {
+    if buf.is_empty() {
+        return None;
+    }
+
+    let end = command_end(buf);
+    let line = buf.get(..end).unwrap().to_owned();
+    // Skip the terminator too, if there is one
+    buf.drain(..buf.len().min(end + 1));
+    Some(line)
+}
+
+/// Removes the first command from the command buffer for the buffer at
+/// index `i` and returns it, or returns [`None`] if the buffer is empty.
+fn next_command(i: usize) -> Option<String> {
+    split_command(CMD_TEXT.write().unwrap().get_mut(i).unwrap())
+}
+
+/// Executes every command currently queued for `local_client_num`, up to
+/// the first `wait`.
+///
+/// The buffer isn't locked while a command runs, so commands are free to
+/// queue more text, which will be executed during the same call.
+pub fn execute(local_client_num: i32, controller_index: i32) {
+    let Some(i) = buffer_index(local_client_num) else {
+        return;
+    };
+
+    {
+        let mut wait_frames = WAIT_FRAMES.write().unwrap();
+        let frames = wait_frames.get_mut(i).unwrap();
+        if *frames > 0 {
+            *frames -= 1;
+            if *frames > 0 {
+                return;
+            }
+        }
+    }
+
+    cmd::reset_alias_expansions();
+    while !is_waiting(i) {
+        let Some(line) = next_command(i) else {
+            break;
+        };
+        cmd::execute_string(local_client_num, controller_index, &line);
+    }
+}
+
+/// Immediately executes every command in `text`, bypassing the command
+/// buffer.
+///
+/// Used for things like config files, whose contents need to be executed
+/// before the command that requested them finishes. If `text` contains a
+/// `wait`, the rest of it is moved to the front of the command buffer for
+/// `local_client_num`.
+pub fn execute_buffer(
+    local_client_num: i32,
+    controller_index: i32,
+    text: &str,
+) {
+    let Some(i) = buffer_index(local_client_num) else {
+        return;
+    };
+
+    // Doesn't reset the alias expansion count, since an alias could
+    // otherwise loop forever by running a config file every time
+    let outermost = BUFFER_STACK.with(|stack| {
+        let mut stack = stack.borrow_mut();
+        stack.push(text.to_owned());
+        stack.len() == 1
+    });
+
+    while !is_waiting(i) {
+        let line = BUFFER_STACK
+            .with(|stack| split_command(stack.borrow_mut().last_mut()?));
+        let Some(line) = line else {
+            break;
+        };
+        cmd::execute_string(local_client_num, controller_index, &line);
+    }
+
+    let rest = BUFFER_STACK.with(|stack| stack.borrow_mut().pop().unwrap());
+    if !rest.is_empty() {
+        DEFERRED_TEXT.with(|deferred| {
+            let mut deferred = deferred.borrow_mut();
+            deferred.push_str(&rest);
+            deferred.push('\n');
+        });
+    }
+
+    if outermost {
+        let deferred =
+            DEFERRED_TEXT.with(|d| core::mem::take(&mut *d.borrow_mut()));
+        if !deferred.is_empty() {
+            // insert_text adds its own newline
+            insert_text(local_client_num, deferred.trim_end_matches('\n'));
+        }
+    }
 }
 
-pub fn add_textln(i: i32, text: &str) {
-    add_text(i, &format!("{}\n", text));
+/// Throws away whatever's left of the text being run by [`execute_buffer`].
+///
+/// Used after an error unwinds out of [`execute_buffer`], since it doesn't
+/// get to clean up after itself. The command buffer itself is left alone.
+pub fn abort_execution() {
+    BUFFER_STACK.with(|stack| stack.borrow_mut().clear());
+    DEFERRED_TEXT.with(|deferred| deferred.borrow_mut().clear());
 }
@@ -80,6 +80,15 @@ pub fn local_client_is_in_game(local_client_num: usize) -> bool {
     get_local_client_connection_state(local_client_num) == Connstate::LOADING
 }
 
+/// Drops `local_client_num`'s connection (if it has one).
+pub fn disconnect(local_client_num: usize) {
+    if let Some(ui) =
+        get_local_client_ui_actives_mut().get_mut(local_client_num)
+    {
+        ui.connection_state = Connstate::DISCONNECTED;
+    }
+}
+
 // TODO - implement
 #[derive(Copy, Clone, Default, Debug)]
 pub struct Snapshot;
@@ -192,12 +201,18 @@ pub fn get_local_client_globals_mut(
 #[derive(Copy, Clone, Default)]
 pub struct ClientStatic {
     vid_config: vid::Config,
+    /// Length of the last frame.
+    frame_time: Duration,
+    /// Sum of the lengths of every frame so far.
+    real_time: Duration,
 }
 
 impl ClientStatic {
     pub fn new() -> Self {
         Self {
             vid_config: vid::Config::new(),
+            frame_time: Duration::ZERO,
+            real_time: Duration::ZERO,
         }
     }
 }
@@ -210,6 +225,13 @@ pub fn init_renderer() {
     render::begin_registration(&mut CLS.write().unwrap().vid_config);
 }
 
+/// Runs a client frame, `msec` after the last one.
+pub fn frame(msec: Duration) {
+    let mut cls = CLS.write().unwrap();
+    cls.frame_time = msec;
+    cls.real_time += msec;
+}
+
 pub fn init_once_for_all_clients() {
     cg::register_dvars();
 }
@@ -1,7 +1,14 @@
 #![allow(dead_code, clippy::missing_trait_methods)]
 
 use arrayvec::ArrayVec;
-use std::{collections::HashMap, sync::RwLock};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        RwLock,
+    },
+};
 extern crate alloc;
 use alloc::rc::Rc;
 use core::{
@@ -87,6 +94,11 @@ impl CmdFunction {
             function,
         }
     }
+
+    /// Runs the command with whatever arguments are currently set.
+    pub fn call(&self) {
+        (self.function)();
+    }
 }
 
 lazy_static! {
@@ -124,11 +136,405 @@ pub fn remove_command(name: &str) {
     CMD_FUNCTIONS.write().unwrap().remove(name).unwrap();
 }
 
+/// Sets the directory and extension of the files that [`complete`] suggests
+/// for the arguments of the command `name` (e.g. `""` and `"cfg"` for
+/// `exec`, or `"maps/mp"` and `"d3dbsp"` for `map`).
+///
+/// Returns [`Err`] if no command with name `name` exists.
+pub fn set_auto_complete(name: &str, dir: &str, ext: &str) -> Result<(), ()> {
+    let mut cmd_functions = CMD_FUNCTIONS.write().unwrap();
+    let Some(cmd) = cmd_functions.get_mut(name) else {
+        return Err(());
+    };
+
+    cmd.auto_complete_dir = dir.trim_matches(['/', '\\']).to_owned();
+    cmd.auto_complete_ext = ext.trim_start_matches('.').to_owned();
+    Ok(())
+}
+
+/// Returns the longest prefix (compared case-insensitively) shared by every
+/// string in `matches`, taken from the first.
+fn common_prefix(matches: &[String]) -> String {
+    let Some((first, rest)) = matches.split_first() else {
+        return String::new();
+    };
+
+    let len = rest.iter().fold(first.len(), |len, m| {
+        first
+            .chars()
+            .zip(m.chars())
+            .take_while(|(a, b)| a.eq_ignore_ascii_case(b))
+            .map(|(a, _)| a.len_utf8())
+            .sum::<usize>()
+            .min(len)
+    });
+    first.get(..len).unwrap_or_default().to_owned()
+}
+
+/// Completes `partial` against `candidates` (which should be sorted).
+///
+/// With a single match, returns the match followed by `suffix`. With several
+/// matches, prints them and returns their common prefix. With no matches,
+/// returns `partial` unchanged.
+fn complete_from(
+    partial: &str,
+    candidates: Vec<String>,
+    suffix: &str,
+) -> String {
+    let partial_lower = partial.to_ascii_lowercase();
+    let matches = candidates
+        .into_iter()
+        .filter(|c| c.to_ascii_lowercase().starts_with(&partial_lower))
+        .collect::<Vec<_>>();
+
+    match matches.as_slice() {
+        [] => partial.to_owned(),
+        [m] => format!("{}{}", m, suffix),
+        _ => {
+            for m in &matches {
+                com::println!(console::Channel::DONT_FILTER, "    {}", m);
+            }
+            common_prefix(&matches)
+        }
+    }
+}
+
+/// Lists the files a command's arguments can be completed with, relative to
+/// its auto-complete directory and without its auto-complete extension.
+fn auto_complete_files(cmd: &CmdFunction) -> Vec<String> {
+    let ext_suffix = format!(".{}", cmd.auto_complete_ext);
+
+    fs::list_files(&cmd.auto_complete_dir, &cmd.auto_complete_ext)
+        .into_iter()
+        .map(|f| {
+            let stem_len = f.len().saturating_sub(ext_suffix.len());
+            f.get(..stem_len).unwrap_or(&f).to_owned()
+        })
+        .collect()
+}
+
+/// Completes the partially-typed console line `text`, as when the user
+/// presses tab.
+///
+/// While the first token is being typed, it's completed against the names
+/// of every command and [`Dvar`](dvar). Once it's complete, the argument is
+/// completed against the files matching the command's auto-complete
+/// directory and extension (see [`set_auto_complete`]), and the value and
+/// domain of a matching [`Dvar`](dvar) are printed. Whenever there's more
+/// than one candidate, the candidates are printed and the text is extended
+/// to their common prefix.
+///
+/// Returns the completed line.
+pub fn complete(text: &str) -> String {
+    // Keep a leading slash, which is allowed (and ignored) on commands
+    let (slash, line) = match text.strip_prefix(['/', '\\']) {
+        Some(line) => (text.get(..1).unwrap_or_default(), line),
+        None => ("", text),
+    };
+    let line = line.trim_start();
+
+    com::println!(console::Channel::DONT_FILTER, "]{}", text);
+
+    let completed = match line.split_once(char::is_whitespace) {
+        None => {
+            let mut names = CMD_FUNCTIONS
+                .read()
+                .unwrap()
+                .keys()
+                .cloned()
+                .chain(dvar::names())
+                .collect::<Vec<_>>();
+            names.sort_unstable_by_key(|n| n.to_ascii_lowercase());
+            names.dedup();
+
+            let completed = complete_from(line, names, " ");
+            dvar::describe(completed.trim_end());
+            completed
+        }
+        Some((name, arg)) => {
+            let arg = arg.trim_start();
+            let completed_arg = match find(name) {
+                Some(cmd) if !cmd.auto_complete_ext.is_empty() => {
+                    complete_from(arg, auto_complete_files(&cmd), "")
+                }
+                _ => {
+                    dvar::describe(name);
+                    arg.to_owned()
+                }
+            };
+            format!("{} {}", name, completed_arg)
+        }
+    };
+
+    format!("{}{}", slash, completed)
+}
+
 thread_local! {
     // Use Rc/RefCell instead of Arc/RwLock since ARGS is thread-local
     static ARGS: Rc<RefCell<CmdArgs>> = Rc::new(RefCell::new(CmdArgs::new()));
 }
 
+/// Maximum depth of nested tokenized strings (e.g., a command that executes
+/// another command via [`execute_string`]). Level 0 is reserved for "no
+/// command currently executing".
+const MAX_NESTING: usize = 8;
+
+/// Maximum number of tokens a single string can be split into, before the
+/// limits of the argv pool are taken into account.
+pub const MAX_TOKENS: usize = 512;
+
+/// Splits `text` into tokens.
+///
+/// Tokens are separated by whitespace. Text inside of double quotes is
+/// treated as a single token (with the quotes removed), and a backslash can
+/// be used to embed a literal double quote inside of a quoted token. A `//`
+/// starts a comment that runs until the end of the text, and `/* ... */`
+/// comments are skipped.
+///
+/// At most `max_tokens` tokens will be produced, and their combined length
+/// (plus a terminator for each token, to mirror the original engine's text
+/// pool) will not exceed `max_chars`.
+///
+/// Returns the tokens and the number of characters of text pool they use.
+fn tokenize(
+    text: &str,
+    max_tokens: usize,
+    max_chars: usize,
+) -> (Vec<String>, usize) {
+    let mut tokens = Vec::new();
+    let mut used_chars = 0;
+    let mut chars = text.chars().peekable();
+
+    'outer: while tokens.len() < max_tokens {
+        // Skip whitespace and comments
+        loop {
+            match chars.peek() {
+                None => break 'outer,
+                Some(&c) if c <= ' ' => {
+                    chars.next();
+                }
+                Some('/') => {
+                    let mut lookahead = chars.clone();
+                    lookahead.next();
+                    match lookahead.peek() {
+                        Some('/') => break 'outer,
+                        Some('*') => {
+                            chars.next();
+                            chars.next();
+                            let mut prev = '\0';
+                            for c in chars.by_ref() {
+                                if prev == '*' && c == '/' {
+                                    break;
+                                }
+                                prev = c;
+                            }
+                        }
+                        _ => break,
+                    }
+                }
+                Some(_) => break,
+            }
+        }
+
+        let mut token = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' if chars.peek() == Some(&'"') => {
+                        token.push('"');
+                        chars.next();
+                    }
+                    _ => token.push(c),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c <= ' ' || c == '"' {
+                    break;
+                }
+
+                if c == '/' {
+                    let mut lookahead = chars.clone();
+                    lookahead.next();
+                    if matches!(lookahead.peek(), Some('/' | '*')) {
+                        break;
+                    }
+                }
+
+                token.push(c);
+                chars.next();
+            }
+        }
+
+        let len = token.chars().count() + 1;
+        if used_chars + len > max_chars {
+            com::warnln!(
+                console::Channel::SYSTEM,
+                "cmd::tokenize_string: text pool exhausted, truncating command"
+            );
+            break;
+        }
+
+        used_chars += len;
+        tokens.push(token);
+    }
+
+    (tokens, used_chars)
+}
+
+/// Tokenizes `text` into at most `max_tokens` arguments and makes them the
+/// current arguments returned by [`argc`] and [`argv`].
+///
+/// Every call must be paired with a call to [`end_tokenized_string`], which
+/// restores the arguments that were current before this call.
+///
+/// # Panics
+///
+/// Throws a fatal error if strings are nested deeper than [`MAX_NESTING`].
+pub fn tokenize_string_with_limit(text: &str, max_tokens: usize) {
+    ARGS.with(|args| {
+        let mut args = args.borrow_mut();
+
+        if args.nesting + 1 >= MAX_NESTING {
+            com::errorln!(
+                com::ErrorParm::FATAL,
+                "cmd::tokenize_string: nesting >= {}",
+                MAX_NESTING
+            );
+            return;
+        }
+
+        let max_tokens = max_tokens.min(
+            args.argv_pool.capacity() - args.total_used_argv_pool as usize,
+        );
+        let max_chars =
+            args.text_pool.capacity() - args.total_used_text_pool as usize;
+        let (tokens, used_chars) = tokenize(text, max_tokens, max_chars);
+
+        args.nesting += 1;
+        let nesting = args.nesting;
+        args.argc[nesting] = tokens.len();
+        args.argshift[nesting] = 0;
+        args.local_client_num[nesting] = -1;
+        args.controller_index[nesting] = 0;
+        args.used_text_pool[nesting] = used_chars as _;
+        args.total_used_argv_pool += tokens.len() as i32;
+        args.total_used_text_pool += used_chars as i32;
+
+        args.argv.truncate(nesting);
+        while args.argv.len() < nesting {
+            args.argv.push(Vec::new());
+        }
+        args.argv.push(tokens);
+    });
+}
+
+/// Tokenizes `text` and makes the result the current arguments returned by
+/// [`argc`] and [`argv`].
+///
+/// See [`tokenize_string_with_limit`].
+pub fn tokenize_string(text: &str) {
+    tokenize_string_with_limit(text, MAX_TOKENS);
+}
+
+/// Discards the arguments created by the last call to [`tokenize_string`]
+/// and restores the ones that were current before it.
+pub fn end_tokenized_string() {
+    ARGS.with(|args| {
+        let mut args = args.borrow_mut();
+        if args.nesting == 0 {
+            com::print_errorln!(
+                console::Channel::ERROR,
+                "cmd::end_tokenized_string: no tokenized string"
+            );
+            return;
+        }
+
+        let nesting = args.nesting;
+        args.total_used_argv_pool -= args.argc[nesting] as i32;
+        args.total_used_text_pool -= args.used_text_pool[nesting];
+        args.argc[nesting] = 0;
+        args.used_text_pool[nesting] = 0;
+        args.argv.truncate(nesting);
+        args.nesting -= 1;
+    });
+}
+
+/// Discards every tokenized string, as if [`end_tokenized_string`] had been
+/// called for each of them.
+///
+/// Used after an error unwinds out of a command, since nothing got the
+/// chance to end its tokenized strings.
+pub fn reset_tokenized_strings() {
+    ARGS.with(|args| args.replace(CmdArgs::new()));
+}
+
+/// Returns the local client number the current command was executed for,
+/// or -1 if there isn't one.
+pub fn local_client_num() -> i32 {
+    ARGS.with(|args| {
+        let args = args.borrow();
+        args.local_client_num[args.nesting]
+    })
+}
+
+/// Returns the controller index the current command was executed for.
+pub fn controller_index() -> i32 {
+    ARGS.with(|args| {
+        let args = args.borrow();
+        args.controller_index[args.nesting]
+    })
+}
+
+/// Tokenizes `text` and executes it as a command.
+///
+/// If no command with the name of the first token exists, the token is
+/// treated as a Dvar name instead (see [`dvar::command`]), and if
+/// that fails too, "Unknown command" is printed.
+///
+/// Returns `true` if either a command or a Dvar handled the text,
+/// `false` otherwise.
+pub fn execute_string(
+    local_client_num: i32,
+    controller_index: i32,
+    text: &str,
+) -> bool {
+    tokenize_string(text);
+    ARGS.with(|args| {
+        let mut args = args.borrow_mut();
+        let nesting = args.nesting;
+        args.local_client_num[nesting] = local_client_num;
+        args.controller_index[nesting] = controller_index;
+    });
+
+    if argc() == 0 {
+        end_tokenized_string();
+        return true;
+    }
+
+    let found = match find(&argv(0)) {
+        Some(cmd) => {
+            cmd.call();
+            true
+        }
+        // Not a command, so see if it's a Dvar instead
+        None => dvar::command(),
+    };
+
+    if !found {
+        com::println!(
+            console::Channel::DONT_FILTER,
+            "Unknown command \"{}\"",
+            argv(0)
+        );
+    }
+
+    end_tokenized_string();
+    found
+}
+
 pub fn argc() -> usize {
     // Temporary to take/replace ARGS
     let mut args = CmdArgs::new();
@@ -180,3 +586,313 @@ pub fn argv(idx: usize) -> String {
     // And return acquired arg
     argv
 }
+
+/// Maximum depth of config files executing other config files, to keep a
+/// config that (directly or indirectly) executes itself from recursing
+/// forever.
+const MAX_EXEC_DEPTH: usize = 16;
+
+/// Current depth of nested [`exec`] calls.
+static EXEC_DEPTH: AtomicUsize = AtomicUsize::new(0);
+
+// Lowers EXEC_DEPTH again when dropped, so that an error thrown while a
+// config is executing (which unwinds straight past the end of exec) doesn't
+// leave the depth raised for good
+struct ExecDepthGuard;
+
+impl ExecDepthGuard {
+    fn enter() -> Self {
+        EXEC_DEPTH.fetch_add(1, Ordering::SeqCst);
+        Self
+    }
+}
+
+impl Drop for ExecDepthGuard {
+    fn drop(&mut self) {
+        EXEC_DEPTH.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Name of the config file executed by `execdefault`.
+pub const DEFAULT_CONFIG: &str = "default_mp.cfg";
+
+/// Reads `filename` through [`fs::read_file`] and executes its contents.
+///
+/// A `.cfg` extension is added to `filename` if it doesn't have one.
+/// Commands are executed immediately (see [`cbuf::execute_buffer`]), so any
+/// `exec`s inside of the file are nested within this one.
+///
+/// Returns [`Err`] if the file couldn't be read, or if executing it would
+/// exceed [`MAX_EXEC_DEPTH`].
+pub fn exec(
+    local_client_num: i32,
+    controller_index: i32,
+    filename: &str,
+) -> Result<(), ()> {
+    let mut path = PathBuf::from(filename);
+    if path.extension().is_none() {
+        path.set_extension("cfg");
+    }
+
+    if EXEC_DEPTH.load(Ordering::SeqCst) >= MAX_EXEC_DEPTH {
+        com::warnln!(
+            console::Channel::FILES,
+            "exec {}: nested too deeply (max depth is {}), not executing",
+            path.display(),
+            MAX_EXEC_DEPTH
+        );
+        return Err(());
+    }
+
+    let Ok(file) = fs::read_file(&path) else {
+        com::println!(
+            console::Channel::FILES,
+            "couldn't exec {}",
+            path.display()
+        );
+        return Err(());
+    };
+
+    let text = String::from_utf8_lossy(&file).into_owned();
+    // Release the file (and its spot in the load stack) before executing,
+    // since the file might exec others
+    drop(file);
+
+    com::println!(console::Channel::DONT_FILTER, "execing {}", path.display());
+
+    let _depth = ExecDepthGuard::enter();
+    cbuf::execute_buffer(local_client_num, controller_index, &text);
+    Ok(())
+}
+
+fn exec_f() {
+    if argc() != 2 {
+        com::println!(
+            console::Channel::DONT_FILTER,
+            "exec <filename> : execute a script file"
+        );
+        return;
+    }
+
+    let _ = exec(local_client_num().max(0), controller_index(), &argv(1));
+}
+
+fn exec_default_f() {
+    let _ = exec(
+        local_client_num().max(0),
+        controller_index(),
+        DEFAULT_CONFIG,
+    );
+}
+
+/// Returns every argument from `start` on, separated by spaces.
+pub fn args_from(start: usize) -> String {
+    (start..argc()).map(argv).collect::<Vec<_>>().join(" ")
+}
+
+/// Maximum number of times aliases (and `vstr`s) can be expanded without
+/// a frame passing, to keep an alias that (directly or indirectly) runs
+/// itself from looping forever. Aliases that loop with a `wait` in between
+/// are fine.
+const MAX_ALIAS_EXPANSIONS: usize = 256;
+
+/// Number of alias expansions since the last call to
+/// [`reset_alias_expansions`].
+static ALIAS_EXPANSIONS: AtomicUsize = AtomicUsize::new(0);
+
+lazy_static! {
+    static ref ALIASES: RwLock<HashMap<String, String>> =
+        RwLock::new(HashMap::new());
+}
+
+/// Resets the count checked against [`MAX_ALIAS_EXPANSIONS`]. Called once
+/// at init, and then by [`cbuf::execute`] at the start of every frame.
+pub fn reset_alias_expansions() {
+    ALIAS_EXPANSIONS.store(0, Ordering::SeqCst);
+}
+
+/// Inserts `text` at the front of the command buffer on behalf of alias or
+/// `vstr` `name`, unless doing so would exceed [`MAX_ALIAS_EXPANSIONS`].
+fn expand(name: &str, text: &str) {
+    if ALIAS_EXPANSIONS.fetch_add(1, Ordering::SeqCst) >= MAX_ALIAS_EXPANSIONS {
+        com::warnln!(
+            console::Channel::DONT_FILTER,
+            "{}: recursion limit of {} expansions reached, not executing",
+            name,
+            MAX_ALIAS_EXPANSIONS
+        );
+        return;
+    }
+
+    cbuf::insert_text(local_client_num().max(0), text);
+}
+
+/// Returns the commands alias `name` runs, or [`None`] if `name` isn't an
+/// alias.
+pub fn alias(name: &str) -> Option<String> {
+    ALIASES.read().unwrap().get(name).cloned()
+}
+
+// Registered as the function of every alias. The alias is identified by
+// the name it was invoked by.
+fn run_alias_f() {
+    let name = argv(0);
+    if let Some(text) = alias(&name) {
+        expand(&name, &text);
+    }
+}
+
+/// Makes `name` a command that runs `text` (which may contain several
+/// commands separated by semicolons). Redefining an existing alias replaces
+/// its commands.
+///
+/// Returns [`Err`] if `name` is already taken by a command or a Dvar.
+pub fn set_alias(name: &str, text: &str) -> Result<(), ()> {
+    if alias(name).is_none() && (exists(name) || dvar::exists(name)) {
+        com::println!(
+            console::Channel::DONT_FILTER,
+            "alias: \"{}\" is already a command or dvar",
+            name
+        );
+        return Err(());
+    }
+
+    let new = ALIASES
+        .write()
+        .unwrap()
+        .insert(name.to_owned(), text.to_owned())
+        .is_none();
+    if new {
+        add_command_internal(name, run_alias_f).unwrap();
+    }
+    Ok(())
+}
+
+/// Removes alias `name`.
+///
+/// Returns [`Err`] if `name` isn't an alias.
+pub fn remove_alias(name: &str) -> Result<(), ()> {
+    if ALIASES.write().unwrap().remove(name).is_none() {
+        return Err(());
+    }
+
+    remove_command(name);
+    Ok(())
+}
+
+fn alias_f() {
+    match argc() {
+        0 | 1 => {
+            com::println!(
+                console::Channel::DONT_FILTER,
+                "USAGE: alias <name> [commands]"
+            );
+        }
+        2 => {
+            let name = argv(1);
+            match alias(&name) {
+                Some(text) => com::println!(
+                    console::Channel::DONT_FILTER,
+                    "{} : \"{}^7\"",
+                    name,
+                    text
+                ),
+                None => com::println!(
+                    console::Channel::DONT_FILTER,
+                    "alias \"{}\" doesn\'t exist",
+                    name
+                ),
+            }
+        }
+        _ => {
+            let _ = set_alias(&argv(1), &args_from(2));
+        }
+    }
+}
+
+fn unalias_f() {
+    if argc() != 2 {
+        com::println!(console::Channel::DONT_FILTER, "USAGE: unalias <name>");
+        return;
+    }
+
+    let name = argv(1);
+    if remove_alias(&name).is_err() {
+        com::println!(
+            console::Channel::DONT_FILTER,
+            "alias \"{}\" doesn\'t exist",
+            name
+        );
+    }
+}
+
+fn alias_list_f() {
+    let filter = argv(1);
+    let mut aliases = ALIASES
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|(name, _)| {
+            filter.is_empty() || com::filter(&filter, name, false)
+        })
+        .map(|(name, text)| (name.clone(), text.clone()))
+        .collect::<Vec<_>>();
+    aliases.sort_unstable();
+
+    for (name, text) in &aliases {
+        com::println!(
+            console::Channel::DONT_FILTER,
+            "{} : \"{}^7\"",
+            name,
+            text
+        );
+    }
+    com::println!(
+        console::Channel::DONT_FILTER,
+        "{} total aliases",
+        aliases.len()
+    );
+}
+
+fn vstr_f() {
+    if argc() != 2 {
+        com::println!(
+            console::Channel::DONT_FILTER,
+            "vstr <variablename> : execute a variable command"
+        );
+        return;
+    }
+
+    let name = argv(1);
+    match dvar::get_string(&name) {
+        Some(text) => expand(&name, &text),
+        None => com::println!(
+            console::Channel::DONT_FILTER,
+            "vstr: \"{}\" isn\'t a string dvar",
+            name
+        ),
+    }
+}
+
+fn wait_f() {
+    let frames = if argc() > 1 {
+        argv(1).parse::<usize>().unwrap_or(1).max(1)
+    } else {
+        1
+    };
+
+    cbuf::wait(local_client_num().max(0), frames);
+}
+
+/// Adds the commands for the command subsystem itself.
+pub fn init() {
+    reset_alias_expansions();
+    add_command_internal("exec", exec_f).unwrap();
+    add_command_internal("execdefault", exec_default_f).unwrap();
+    set_auto_complete("exec", "", "cfg").unwrap();
+    add_command_internal("alias", alias_f).unwrap();
+    add_command_internal("unalias", unalias_f).unwrap();
+    add_command_internal("aliaslist", alias_list_f).unwrap();
+    add_command_internal("vstr", vstr_f).unwrap();
+    add_command_internal("wait", wait_f).unwrap();
+}
@@ -3,17 +3,26 @@
 use crate::{console::Channel, util::EasierAtomic, *};
 use arrayvec::ArrayVec;
 use core::{
+    cell::Cell,
+    panic::AssertUnwindSafe,
     sync::atomic::{AtomicU64, AtomicUsize},
     time::Duration,
 };
 use lazy_static::lazy_static;
 use std::{
-    fs::File,
+    path::{Path, PathBuf},
     sync::{Mutex, RwLock},
 };
 extern crate alloc;
 use alloc::sync::Arc;
 
+mod logfile;
+pub use logfile::{
+    close_log_file, flush_log_file, log_file_open, open_log_file, LOG_FILE_NAME,
+};
+mod parse;
+pub use parse::*;
+
 pub static ERROR_ENTERED: AtomicBool = AtomicBool::new(false);
 
 #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
@@ -28,74 +37,6 @@ pub enum ErrorParm {
     LOCALIZATION,
 }
 
-#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
-#[derive(Copy, Clone, Debug)]
-pub enum ParseTokenType {
-    UNKNOWN,
-    NUMBER,
-    STRING,
-    NAME,
-    HASH,
-    PUNCTUATION,
-}
-
-#[allow(clippy::struct_excessive_bools)]
-struct ParseInfo {
-    token: String,
-    token_type: ParseTokenType,
-    lines: i32,
-    unget_token: bool,
-    space_delimited: bool,
-    keep_string_quotes: bool,
-    csv: bool,
-    negative_numbers: bool,
-    error_prefix: String,
-    warning_prefix: String,
-    backup_lines: i32,
-    backup_text: String,
-    parse_file: String,
-}
-
-impl ParseInfo {
-    const fn new() -> Self {
-        Self {
-            token: String::new(),
-            token_type: ParseTokenType::UNKNOWN,
-            lines: 1,
-            unget_token: false,
-            space_delimited: true,
-            keep_string_quotes: false,
-            csv: false,
-            negative_numbers: false,
-            error_prefix: String::new(),
-            warning_prefix: String::new(),
-            backup_lines: 0,
-            backup_text: String::new(),
-            parse_file: String::new(),
-        }
-    }
-}
-
-struct ParseThreadInfo {
-    parse_info: ArrayVec<ParseInfo, 16>,
-    parse_info_num: isize,
-    token_pos: isize,
-    prev_token_pos: isize,
-    line: String,
-}
-
-impl ParseThreadInfo {
-    fn new() -> Self {
-        Self {
-            parse_info: ArrayVec::new(),
-            parse_info_num: 0,
-            token_pos: 0,
-            prev_token_pos: 0,
-            line: String::new(),
-        }
-    }
-}
-
 lazy_static! {
     static ref PRINT_LOCK: Arc<Mutex<()>> = Arc::new(Mutex::new(()));
 }
@@ -123,7 +64,11 @@ pub mod _internals {
         _message_type: super::MessageType,
         arguments: core::fmt::Arguments,
     ) {
-        std::print!("({:?}) - {}", channel, arguments);
+        let text = arguments.to_string();
+        if channel != super::Channel::LOGFILEONLY {
+            std::print!("({:?}) - {}", channel, text);
+        }
+        super::logfile::log_print(&text);
     }
 
     cfg_if! {
@@ -169,12 +114,10 @@ pub mod _internals {
         );
     }
 
-    // Also needs to be actually implemented
-    // Currently just a wrapper for panic
-    #[allow(clippy::panic, clippy::needless_pass_by_value)]
+    #[allow(clippy::needless_pass_by_value)]
     #[doc(hidden)]
     pub fn _error(err_type: super::ErrorParm, arguments: core::fmt::Arguments) {
-        panic!("{} ({:?})", arguments, err_type);
+        super::throw_error(err_type, &arguments.to_string());
     }
 }
 
@@ -380,21 +323,17 @@ macro_rules! __com_print_errorln {
 }
 pub(crate) use __com_print_errorln as print_errorln;
 
-lazy_static! {
-    static ref LOG_FILE: Arc<RwLock<Option<File>>> =
-        Arc::new(RwLock::new(None));
-}
-
-// Check if log file is open
-pub fn log_file_open() -> bool {
-    return LOG_FILE.clone().read().unwrap().is_some();
-}
-
 /// Throws an error. Not the same as [`com::print_error!`].
 ///
-/// # Panics
+/// [`ErrorParm::FATAL`] errors shut the engine down through [`sys::error`].
+/// Any other error unwinds back to [`com::frame`], which drops the current
+/// map or connection, prints the message, and carries on with the next
+/// frame. Errors thrown outside of [`com::frame`] (e.g. during
+/// initialization, or on another thread) have nowhere to unwind to, so
+/// they're always fatal.
 ///
-/// Currently always panics.
+/// Since the error unwinds, any write locks held by the functions it
+/// unwinds through are poisoned, so avoid throwing while holding one.
 ///
 /// # Example
 ///
@@ -412,9 +351,7 @@ pub(crate) use __com_error as error;
 /// Throws an error with a newline appended. Not the same as
 /// [`com::print_error!`].
 ///
-/// # Panics
-///
-/// Currently always panics.
+/// See [`com::error!`] for how the error is handled.
 ///
 /// # Example
 ///
@@ -431,30 +368,69 @@ macro_rules! __com_errorln {
 }
 pub(crate) use __com_errorln as errorln;
 
-// Implement these two later
-// (not integral to the program, look annoying to implement)
-#[allow(unused_variables, unreachable_code, clippy::todo)]
-pub const fn filter(string: &str, name: &str, case_sensitive: bool) -> bool {
-    return true;
-
-    todo!("com::filter");
-}
-
-#[allow(
-    unused_variables,
-    unreachable_code,
-    clippy::todo,
-    clippy::needless_pass_by_value
-)]
-pub const fn dvar_dump(channel: i32, param_2: &str) {
-    return;
+/// Checks whether `name` matches the glob pattern `filter`.
+///
+/// `*` matches any sequence of characters (including an empty one), and `?`
+/// matches any single character. Every other character only matches itself,
+/// ignoring case unless `case_sensitive` is set.
+#[allow(clippy::indexing_slicing)]
+pub fn filter(filter: &str, name: &str, case_sensitive: bool) -> bool {
+    let normalize = |s: &str| -> Vec<char> {
+        if case_sensitive {
+            s.chars().collect()
+        } else {
+            s.chars().flat_map(char::to_lowercase).collect()
+        }
+    };
+    let filter = normalize(filter);
+    let name = normalize(name);
+
+    let (mut f, mut n) = (0, 0);
+    // Position of the last '*' seen in the filter, and the position in the
+    // name it's currently matched up to. On a mismatch, we backtrack there
+    // and let the '*' swallow one more character.
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match filter.get(f) {
+            Some('*') => {
+                f += 1;
+                star = Some((f, n));
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                f += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_f, star_n)) => {
+                    f = star_f;
+                    n = star_n + 1;
+                    star = Some((star_f, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
 
-    todo!("com::dvar_dump");
+    filter[f..].iter().all(|&c| c == '*')
 }
 
-lazy_static! {
-    static ref G_PARSE: Arc<RwLock<ArrayVec<ParseThreadInfo, 16>>> =
-        Arc::new(RwLock::new(ArrayVec::new()));
+/// Prints every dvar whose name matches `pattern` (or every dvar, if
+/// `pattern` is empty) to `channel`, along with its values, domain, flags,
+/// and description.
+pub fn dvar_dump(channel: Channel, pattern: &str) {
+    self::println!(
+        channel,
+        "=============================== DVAR DUMP \
+         ========================================"
+    );
+    let count = dvar::dump(channel, pattern);
+    self::println!(channel, "\n{} total dvars", count);
+    self::println!(
+        channel,
+        "=============================== END DVAR DUMP \
+         ====================================="
+    );
 }
 
 pub const fn get_official_build_name_r() -> &'static str {
@@ -465,9 +441,48 @@ pub const fn get_build_display_name() -> &'static str {
     "Call of Duty Singleplayer - Ship"
 }
 
-// TODO - use host build info instead of hardcoding
+/// Returns the engine's version and the git revision it was built from, with
+/// `-dirty` appended if the working tree had uncommitted changes.
 pub const fn get_build_version() -> &'static str {
-    "7.0.61 CL(794515) CODPCAB-V6 Fri Nov 05 11:33:52 2010"
+    if get_build_dirty() {
+        concat!(
+            env!("CARGO_PKG_VERSION"),
+            " ",
+            env!("OPEN_T5_GIT_REV"),
+            "-dirty"
+        )
+    } else {
+        concat!(env!("CARGO_PKG_VERSION"), " ", env!("OPEN_T5_GIT_REV"))
+    }
+}
+
+/// Returns the (abbreviated) git revision the engine was built from, or
+/// `unknown` if it wasn't built from a git checkout.
+pub const fn get_build_git_revision() -> &'static str {
+    env!("OPEN_T5_GIT_REV")
+}
+
+/// Returns whether the working tree had uncommitted changes when the engine
+/// was built.
+pub const fn get_build_dirty() -> bool {
+    matches!(env!("OPEN_T5_GIT_DIRTY").as_bytes(), b"true")
+}
+
+/// Returns the cargo profile the engine was built with (e.g. `debug` or
+/// `release`).
+pub const fn get_build_profile() -> &'static str {
+    env!("OPEN_T5_BUILD_PROFILE")
+}
+
+/// Returns the cargo features the engine was built with, separated by
+/// spaces.
+pub const fn get_build_features() -> &'static str {
+    env!("OPEN_T5_BUILD_FEATURES")
+}
+
+/// Returns the version of rustc the engine was built with.
+pub const fn get_build_rustc_version() -> &'static str {
+    env!("OPEN_T5_RUSTC_VERSION")
 }
 
 pub const fn get_build_name() -> &'static str {
@@ -539,9 +554,9 @@ pub const fn get_build_os() -> &'static str {
     "unknown"
 }
 
-// TODO - get at compile time instead of hardcoding
+/// Returns the date (in UTC) the engine was built on, as `YYYY-MM-DD`.
 pub const fn get_build_date() -> &'static str {
-    "Nov  5 2010"
+    env!("OPEN_T5_BUILD_DATE")
 }
 
 static FILE_ACCESSED: AtomicUsize = AtomicUsize::new(0);
@@ -579,17 +594,6 @@ fn init_dvars() {
     )
     .unwrap();
 
-    dvar::register_bool(
-        "onlinegame",
-        true,
-        dvar::DvarFlags::READ_ONLY,
-        Some(
-            "Current game is an online game with stats, custom classes, \
-             unlocks",
-        ),
-    )
-    .unwrap();
-
     dvar::register_bool(
         "useFastFile",
         true,
@@ -598,6 +602,19 @@ fn init_dvars() {
     )
     .unwrap();
 
+    dvar::register_int(
+        "logfile",
+        0,
+        Some(0),
+        Some(2),
+        dvar::DvarFlags::empty(),
+        Some(
+            "Write to log file - 0 = disabled, 1 = buffered file write, 2 = \
+             sync every write",
+        ),
+    )
+    .unwrap();
+
     dvar::register_bool(
         "sys_smp_allowed",
         1 < sys::get_logical_cpu_count(),
@@ -605,6 +622,8 @@ fn init_dvars() {
         Some("Allow multi-threading"),
     )
     .unwrap();
+
+    lazy_static::initialize(&COM_DVARS);
 }
 
 fn init_try_block_function() {
@@ -617,13 +636,25 @@ fn init_try_block_function() {
         console::Channel::SYSTEM,
         "{build_version} {build_name} build {os}-{arch} {build_date}"
     );
+    cmd::init();
+    add_commands();
     init_dvars();
+    sv::init();
+    // Apply +set before the filesystem starts, so that things like
+    // fs_basepath and fs_game can be overridden
+    startup_variable(None);
     fs::init_filesystem(true);
+    logfile::init();
+    cbuf::execute_buffer(0, 0, "execdefault");
+    cbuf::execute_buffer(0, 0, &format!("exec {}", CONFIG_FILE));
+    // And again, so that the command line overrides the configs
+    startup_variable(None);
     cl::init_once_for_all_clients();
     render::init_threads();
     cl::init_renderer();
     render::begin_remote_screen_update();
     render::end_remote_screen_update();
+    add_startup_commands();
     self::println!(
         console::Channel::SYSTEM,
         "--- Common Initialization Complete ---"
@@ -640,9 +671,149 @@ pub const fn get_icon_rgba() -> Option<Vec<u8>> {
     None
 }
 
-// TODO - implement
-pub fn startup_variable(name: &str) {
-    com::println!(console::Channel::SYSTEM, "com::startup_variable: {}", name);
+/// The process's command line, split into its parts by
+/// [`parse_command_line`].
+#[derive(Clone, Default, Debug)]
+struct CommandLine {
+    /// Bare words before the first '+' (e.g. "allowdupe").
+    switches: Vec<String>,
+    /// Text of each '+' command, without the '+' (e.g. "set fs_game foo").
+    commands: Vec<String>,
+}
+
+lazy_static! {
+    static ref COMMAND_LINE: RwLock<CommandLine> =
+        RwLock::new(CommandLine::default());
+}
+
+/// Splits `cmdline` into bare switches and '+' commands.
+///
+/// A '+' at the start of a word (outside of quotes) starts a new command,
+/// which runs until the next one, so
+/// `allowdupe +set fs_game "mods/my mod" +map mp_nuked` contains the switch
+/// `allowdupe` and the commands `set fs_game "mods/my mod"` and
+/// `map mp_nuked`.
+pub fn parse_command_line(cmdline: &str) {
+    let mut parsed = CommandLine::default();
+    let mut current = String::new();
+    let mut in_command = false;
+    let mut in_quotes = false;
+    let mut at_word_start = true;
+
+    let mut finish = |text: &str, in_command: bool| {
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+        if in_command {
+            parsed.commands.push(text.to_owned());
+        } else {
+            parsed
+                .switches
+                .extend(text.split_whitespace().map(ToOwned::to_owned));
+        }
+    };
+
+    for c in cmdline.chars() {
+        if c == '+' && at_word_start && !in_quotes {
+            finish(&current, in_command);
+            current.clear();
+            in_command = true;
+            continue;
+        }
+
+        if c == '"' {
+            in_quotes = !in_quotes;
+        }
+        at_word_start = c.is_whitespace() && !in_quotes;
+        current.push(c);
+    }
+    finish(&current, in_command);
+
+    *COMMAND_LINE.write().unwrap() = parsed;
+}
+
+/// Returns `true` if `name` was given on the command line, either as a
+/// bare switch (`nosnd`) or as a command without arguments (`+nosnd`).
+pub fn command_line_has(name: &str) -> bool {
+    let command_line = COMMAND_LINE.read().unwrap();
+    command_line
+        .switches
+        .iter()
+        .chain(command_line.commands.iter())
+        .any(|s| s.eq_ignore_ascii_case(name))
+}
+
+/// Returns the value the command line sets Dvar `name` to with `+set`, if
+/// any. If it's set more than once, the last value wins.
+pub fn command_line_dvar(name: &str) -> Option<String> {
+    let commands = COMMAND_LINE.read().unwrap().commands.clone();
+    commands.iter().rev().find_map(|line| {
+        cmd::tokenize_string(line);
+        let value = if is_set_command(&cmd::argv(0))
+            && cmd::argv(1).eq_ignore_ascii_case(name)
+        {
+            Some(cmd::argv(2))
+        } else {
+            None
+        };
+        cmd::end_tokenized_string();
+        value
+    })
+}
+
+fn is_set_command(name: &str) -> bool {
+    name.eq_ignore_ascii_case("set") || name.eq_ignore_ascii_case("seta")
+}
+
+/// Executes the `+set` commands from the command line, so that they apply
+/// before (and, when called again later, override) anything else. With a
+/// `name`, only the ones that set that Dvar are executed.
+///
+/// `+set`s for Dvars already registered as write-protected or read-only
+/// are skipped, since setting them would only fail. This keeps the second
+/// call from complaining about e.g. `fs_basepath`, which the first call set
+/// before the filesystem registered it as write-protected.
+pub fn startup_variable(name: Option<&str>) {
+    let commands = COMMAND_LINE.read().unwrap().commands.clone();
+    for line in &commands {
+        cmd::tokenize_string(line);
+        let dvar_name = cmd::argv(1);
+        let matches = is_set_command(&cmd::argv(0))
+            && name.map_or(true, |n| dvar_name.eq_ignore_ascii_case(n))
+            && !dvar::get_flags(&dvar_name).is_some_and(|f| {
+                f.intersects(
+                    dvar::DvarFlags::WRITE_PROTECTED
+                        | dvar::DvarFlags::READ_ONLY,
+                )
+            });
+        cmd::end_tokenized_string();
+
+        if matches {
+            cmd::execute_string(0, 0, line);
+        }
+    }
+}
+
+/// Queues every '+' command from the command line other than `+set` (which
+/// [`startup_variable`] handles) to be executed.
+///
+/// Returns `true` if any commands were queued.
+pub fn add_startup_commands() -> bool {
+    let commands = COMMAND_LINE.read().unwrap().commands.clone();
+    let mut added = false;
+    for line in &commands {
+        cmd::tokenize_string(line);
+        let is_set = is_set_command(&cmd::argv(0));
+        cmd::end_tokenized_string();
+
+        if !is_set {
+            cbuf::add_textln(0, line);
+            added = true;
+        }
+    }
+
+    added
 }
 
 lazy_static! {
@@ -657,17 +828,334 @@ pub fn force_safe_mode() {
     SAFE_MODE.store(true, Ordering::SeqCst);
 }
 
+/// Largest amount of time a single frame can advance the game by, so that
+/// a hitch (or sitting in a debugger) doesn't make everything jump ahead.
+const MAX_FRAME_MSEC: u64 = 200;
+
+// Game time in milliseconds, advanced by each frame's (scaled) length
 static FRAME_TIME: AtomicU64 = AtomicU64::new(0);
 
+/// Returns the game time, i.e. the sum of the lengths of every frame run so
+/// far, after `timescale` (or `com_fixedtime`) has been applied.
 pub fn frame_time() -> Duration {
     Duration::from_millis(FRAME_TIME.load_relaxed())
 }
 
+lazy_static! {
+    // Real time the last frame started at, from sys::milliseconds
+    static ref LAST_FRAME_REAL_TIME: Mutex<Option<isize>> = Mutex::new(None);
+    // Registered by init_dvars
+    static ref COM_DVARS: ComDvars = ComDvars::register();
+}
+
+// Dvars read often enough (e.g. every frame) to keep handles to
+struct ComDvars {
+    onlinegame: dvar::DvarHandle<bool>,
+    com_maxfps: dvar::DvarHandle<i32>,
+    timescale: dvar::DvarHandle<f32>,
+    com_fixedtime: dvar::DvarHandle<i32>,
+}
+
+impl ComDvars {
+    fn register() -> Self {
+        Self {
+            onlinegame: dvar::register_bool(
+                "onlinegame",
+                true,
+                dvar::DvarFlags::READ_ONLY,
+                Some(
+                    "Current game is an online game with stats, custom \
+                     classes, unlocks",
+                ),
+            )
+            .unwrap(),
+            com_maxfps: dvar::register_int(
+                "com_maxfps",
+                85,
+                Some(0),
+                Some(1000),
+                dvar::DvarFlags::ARCHIVE,
+                Some("Cap frames per second"),
+            )
+            .unwrap(),
+            timescale: dvar::register_float(
+                "timescale",
+                1.0,
+                Some(0.001),
+                Some(1000.0),
+                dvar::DvarFlags::CHEAT_PROTECTED,
+                Some("Scale time of each frame"),
+            )
+            .unwrap(),
+            com_fixedtime: dvar::register_int(
+                "com_fixedtime",
+                0,
+                Some(0),
+                Some(1000),
+                dvar::DvarFlags::CHEAT_PROTECTED,
+                Some(
+                    "Advance each frame by exactly this many milliseconds, no \
+                     matter how long it took (0 to disable)",
+                ),
+            )
+            .unwrap(),
+        }
+    }
+}
+
+/// Checks whether the current game is an online game (i.e. the value of
+/// `onlinegame`).
+pub fn online_game() -> bool {
+    COM_DVARS.onlinegame.get()
+}
+
+// Sleeps until enough real time has passed since the last frame to honor
+// com_maxfps, and returns how many milliseconds have passed
+#[allow(clippy::integer_division)]
+fn wait_for_frame() -> u64 {
+    // Fixed-step frames shouldn't depend on the real time at all
+    let min_msec = if COM_DVARS.com_fixedtime.get() > 0 {
+        0
+    } else {
+        match COM_DVARS.com_maxfps.get() {
+            fps if fps > 0 => 1000 / fps as isize,
+            _ => 1,
+        }
+    };
+
+    let mut last = LAST_FRAME_REAL_TIME.lock().unwrap();
+    loop {
+        let now = sys::milliseconds();
+        let last_time = *last.get_or_insert(now);
+        // The clock went backwards, so start counting over
+        if now < last_time {
+            *last = Some(now);
+            continue;
+        }
+
+        let msec = now - last_time;
+        if msec >= min_msec {
+            *last = Some(now);
+            return msec as u64;
+        }
+
+        std::thread::sleep(Duration::from_millis((min_msec - msec) as u64));
+    }
+}
+
+// Converts the real length of a frame into how far it advances the game
+#[allow(
+    clippy::cast_sign_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss
+)]
+fn modify_msec(real_msec: u64) -> u64 {
+    let fixed_time = COM_DVARS.com_fixedtime.get();
+    if fixed_time > 0 {
+        return fixed_time as u64;
+    }
+
+    let msec = (real_msec as f32 * COM_DVARS.timescale.get()) as u64;
+    msec.clamp(1, MAX_FRAME_MSEC)
+}
+
+/// Name of the config file archived dvars are saved to on quit and loaded
+/// from on startup.
+pub const CONFIG_FILE: &str = "config_mp.cfg";
+
+/// Writes every archived dvar that differs from its default to `filename`.
+pub fn write_config_to_file(filename: impl AsRef<Path>) -> Result<(), ()> {
+    let text = format!(
+        "// generated by {}, do not modify\n{}",
+        get_build_name(),
+        dvar::write_variables()
+    );
+
+    match fs::write_file(&filename, text.as_bytes()) {
+        Ok(_) => Ok(()),
+        Err(_) => {
+            self::println!(
+                console::Channel::FILES,
+                "Couldn't write {}.",
+                filename.as_ref().display()
+            );
+            Err(())
+        }
+    }
+}
+
+fn write_config_f() {
+    if cmd::argc() != 2 {
+        self::println!(
+            console::Channel::DONT_FILTER,
+            "Usage: writeconfig <filename>"
+        );
+        return;
+    }
+
+    let mut filename = PathBuf::from(cmd::argv(1));
+    if filename.extension().is_none() {
+        filename.set_extension("cfg");
+    }
+
+    self::println!(
+        console::Channel::DONT_FILTER,
+        "Writing {}.",
+        filename.display()
+    );
+    let _ = write_config_to_file(filename);
+}
+
+fn version_f() {
+    self::println!(
+        console::Channel::DONT_FILTER,
+        "{} {} build {}-{} {}",
+        get_build_version(),
+        get_build_name(),
+        get_build_os(),
+        get_build_arch(),
+        get_build_date()
+    );
+    self::println!(
+        console::Channel::DONT_FILTER,
+        "revision: {}{}",
+        get_build_git_revision(),
+        if get_build_dirty() { " (dirty)" } else { "" }
+    );
+    self::println!(
+        console::Channel::DONT_FILTER,
+        "profile:  {}",
+        get_build_profile()
+    );
+    let features = get_build_features();
+    self::println!(
+        console::Channel::DONT_FILTER,
+        "features: {}",
+        if features.is_empty() {
+            "none"
+        } else {
+            features
+        }
+    );
+    self::println!(
+        console::Channel::DONT_FILTER,
+        "compiler: {}",
+        get_build_rustc_version()
+    );
+}
+
+fn add_commands() {
+    cmd::add_command_internal("writeconfig", write_config_f).unwrap();
+    cmd::add_command_internal("version", version_f).unwrap();
+}
+
 pub fn quit_f() -> ! {
     self::println!(console::Channel::DONT_FILTER, "quitting...");
-    if ERROR_ENTERED.load(Ordering::Relaxed) == false {}
+    // Don't save anything if we're quitting because something went wrong,
+    // since the dvars could be in a bad state
+    if ERROR_ENTERED.load(Ordering::Relaxed) == false {
+        let _ = write_config_to_file(CONFIG_FILE);
+    }
+    close_log_file();
     sys::quit();
 }
 
-#[allow(clippy::missing_const_for_fn)]
-pub fn frame() {}
+thread_local! {
+    // Whether com::frame is running on this thread, i.e. whether there's
+    // anywhere for a recoverable error to unwind to
+    static IN_FRAME: Cell<bool> = Cell::new(false);
+}
+
+// Payload of the unwind from com::error! back to com::frame
+struct ErrorUnwind(ErrorParm);
+
+fn throw_error(err_type: ErrorParm, message: &str) -> ! {
+    // If handling the last error is what threw this one, trying to recover
+    // would probably just throw again
+    if ERROR_ENTERED.swap(true, Ordering::SeqCst) {
+        sys::error(&format!(
+            "recursive error after: {}",
+            ERROR_MESSAGE.read().unwrap()
+        ));
+    }
+
+    let message = message.trim_end();
+    *ERROR_MESSAGE.write().unwrap() = message.to_owned();
+
+    if matches!(err_type, ErrorParm::FATAL) || !IN_FRAME.with(Cell::get) {
+        logfile::log_print(&format!("ERROR: {}\n", message));
+        close_log_file();
+    }
+
+    match err_type {
+        ErrorParm::FATAL => sys::error(message),
+        _ if IN_FRAME.with(Cell::get) == false => sys::error(message),
+        _ => std::panic::resume_unwind(Box::new(ErrorUnwind(err_type))),
+    }
+}
+
+// Cleans up after an error that unwound to com::frame, so that the next
+// frame starts from a clean slate
+fn recover_from_error(err_type: ErrorParm) {
+    let message = ERROR_MESSAGE.read().unwrap().clone();
+
+    // Whatever was running when the error was thrown didn't get the chance
+    // to clean up after itself
+    cbuf::abort_execution();
+    cmd::reset_tokenized_strings();
+    parse::reset_sessions();
+
+    // Disconnects only affect the client, anything else takes down the
+    // local server too
+    if !matches!(
+        err_type,
+        ErrorParm::DISCONNECT | ErrorParm::SERVERDISCONNECT
+    ) {
+        sv::shutdown(&format!("Server crashed: {}", message));
+    }
+    for local_client_num in 0..cbuf::MAX_LOCAL_CLIENTS {
+        cl::disconnect(local_client_num);
+    }
+
+    self::print_errorln!(Channel::ERROR, "{}", message);
+    ERROR_ENTERED.store(false, Ordering::SeqCst);
+}
+
+/// Runs a single frame.
+///
+/// Waits until `com_maxfps` allows another frame, runs the command buffer,
+/// and then advances the game time and runs the server and client frames by
+/// however long the frame took, scaled by `timescale`. If `com_fixedtime` is
+/// set, every frame instead advances by exactly that many milliseconds,
+/// without waiting, which keeps automated tests deterministic.
+///
+/// If a non-fatal error is thrown during the frame, the rest of the frame is
+/// skipped, the current map or connection is dropped, and the message is
+/// printed to [`Channel::ERROR`].
+pub fn frame() {
+    IN_FRAME.with(|f| f.set(true));
+    let result = std::panic::catch_unwind(AssertUnwindSafe(run_frame));
+    IN_FRAME.with(|f| f.set(false));
+
+    if let Err(payload) = result {
+        match payload.downcast::<ErrorUnwind>() {
+            Ok(err) => recover_from_error(err.0),
+            // Not one of ours, so keep panicking
+            Err(payload) => std::panic::resume_unwind(payload),
+        }
+    }
+
+    flush_log_file();
+}
+
+fn run_frame() {
+    let real_msec = wait_for_frame();
+    cbuf::execute(0, 0);
+
+    let msec = modify_msec(real_msec);
+    FRAME_TIME.fetch_add(msec, Ordering::Relaxed);
+    let msec = Duration::from_millis(msec);
+
+    sv::frame(msec);
+    cl::frame(msec);
+}
@@ -0,0 +1,223 @@
+// This file implements the log file, which mirrors everything printed
+// through `com::print!` and friends into `console_mp.log`, so that testers
+// have something to attach to bug reports.
+//
+// Prints can come from anywhere, including from functions that hold the lock
+// for DVARS or for the filesystem's file handles, so printing never looks up
+// Dvars and never waits on the filesystem. The `logfile` Dvar is read through
+// a change callback instead, and text that can't be written right away is
+// kept in the log's buffer until the next print or the end of the frame.
+
+use crate::*;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use std::{
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use lazy_static::lazy_static;
+
+/// Name of the log file, relative to the game directory.
+pub const LOG_FILE_NAME: &str = "console_mp.log";
+
+/// Number of bytes a buffered log holds before it's written out anyways.
+const LOG_BUFFER_SIZE: usize = 0x4000;
+
+struct LogFile {
+    fd: fs::Fd,
+    // Whether every print is written out right away (logfile 2), instead of
+    // once per frame (logfile 1)
+    sync: bool,
+    // Text that hasn't been written to the file yet
+    buffer: String,
+    // Whether the next text printed starts a new line, and thus needs a
+    // timestamp
+    at_line_start: bool,
+}
+
+impl LogFile {
+    fn append(&mut self, text: &str) {
+        for line in text.split_inclusive('\n') {
+            if self.at_line_start {
+                self.buffer.push_str(&timestamp(SystemTime::now(), false));
+                self.buffer.push(' ');
+            }
+            push_without_colors(&mut self.buffer, line);
+            self.at_line_start = line.ends_with('\n');
+        }
+    }
+
+    fn flush(&mut self) {
+        while !self.buffer.is_empty() {
+            match fs::try_write(&self.fd, self.buffer.as_bytes()) {
+                Ok(0) => break,
+                Ok(n) => {
+                    self.buffer.drain(..n);
+                }
+                // Somebody's using the file handles, try again later
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+                // Can't print about it without ending up right back here,
+                // so just drop the text instead of letting it pile up
+                Err(_) => self.buffer.clear(),
+            }
+        }
+    }
+}
+
+// Appends `text` to `buffer` without its color codes (a '^' followed by a
+// digit), which only mean something to the console
+fn push_without_colors(buffer: &mut String, text: &str) {
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '^' && chars.peek().is_some_and(char::is_ascii_digit) {
+            chars.next();
+            continue;
+        }
+        buffer.push(c);
+    }
+}
+
+lazy_static! {
+    static ref LOG_FILE: Mutex<Option<LogFile>> = Mutex::new(None);
+}
+
+// Whether the log file has already been opened once this session, in which
+// case it gets appended to instead of overwritten when reopened
+static LOG_FILE_OPENED: AtomicBool = AtomicBool::new(false);
+
+/// Checks whether the log file is open.
+pub fn log_file_open() -> bool {
+    LOG_FILE.lock().unwrap().is_some()
+}
+
+/// Formats `time` (in UTC) as `HH:MM:SS.mmm`, or as
+/// `YYYY-MM-DD HH:MM:SS.mmm` if `with_date` is set.
+#[allow(clippy::integer_division, clippy::arithmetic_side_effects)]
+fn timestamp(time: SystemTime, with_date: bool) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let millis = since_epoch.subsec_millis();
+    let (hours, minutes, seconds) =
+        ((secs / 3600) % 24, (secs / 60) % 60, secs % 60);
+
+    if !with_date {
+        return format!("{hours:02}:{minutes:02}:{seconds:02}.{millis:03}");
+    }
+
+    let (year, month, day) = util::date::civil_from_days(secs / 86400);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} \
+         {hours:02}:{minutes:02}:{seconds:02}.{millis:03}"
+    )
+}
+
+/// Writes `text` to the log file, if it's open.
+pub(super) fn log_print(text: &str) {
+    let mut log_file = LOG_FILE.lock().unwrap();
+    let Some(log) = log_file.as_mut() else {
+        return;
+    };
+
+    log.append(text);
+    if log.sync || log.buffer.len() >= LOG_BUFFER_SIZE {
+        log.flush();
+    }
+}
+
+/// Writes out anything still buffered for the log file.
+///
+/// Called once per frame by [`com::frame`].
+pub fn flush_log_file() {
+    if let Some(log) = LOG_FILE.lock().unwrap().as_mut() {
+        log.flush();
+    }
+}
+
+/// Opens the log file (or reopens it, if `sync` changed) so that it
+/// mirrors everything printed from now on.
+///
+/// The first time it's opened in a session, the log file is overwritten.
+/// After that, it's appended to.
+pub fn open_log_file(sync: bool) -> Result<(), ()> {
+    if let Some(log) = LOG_FILE.lock().unwrap().as_mut() {
+        log.sync = sync;
+        log.flush();
+        return Ok(());
+    }
+
+    // The filesystem might print while opening the file, so LOG_FILE can't
+    // be locked until it's done
+    let fd = if LOG_FILE_OPENED.load(Ordering::SeqCst) {
+        fs::open_file_append(LOG_FILE_NAME)
+    } else {
+        fs::open_file_write(LOG_FILE_NAME)
+    };
+    let Ok(fd) = fd else {
+        com::println!(
+            console::Channel::FILES,
+            "Couldn't open {}.",
+            LOG_FILE_NAME
+        );
+        return Err(());
+    };
+    LOG_FILE_OPENED.store(true, Ordering::SeqCst);
+
+    *LOG_FILE.lock().unwrap() = Some(LogFile {
+        fd,
+        sync,
+        buffer: String::new(),
+        at_line_start: true,
+    });
+    log_print(&format!(
+        "logfile opened on {} UTC by {} ({})\n",
+        timestamp(SystemTime::now(), true),
+        com::get_build_version(),
+        com::get_build_profile()
+    ));
+    Ok(())
+}
+
+/// Writes out anything still buffered for the log file and closes it.
+pub fn close_log_file() {
+    let log = LOG_FILE.lock().unwrap().take();
+    if let Some(mut log) = log {
+        log.append(&format!(
+            "logfile closed on {} UTC\n",
+            timestamp(SystemTime::now(), true)
+        ));
+        log.flush();
+    }
+}
+
+// Opens or closes the log file to match `logfile`'s value
+fn set_log_mode(mode: Option<i32>) {
+    let _ = match mode {
+        Some(1) => open_log_file(false),
+        Some(2) => open_log_file(true),
+        _ => {
+            close_log_file();
+            Ok(())
+        }
+    };
+}
+
+fn logfile_changed(
+    _name: &str,
+    _old: &dvar::DvarValue,
+    new: &dvar::DvarValue,
+    _source: dvar::SetSource,
+) {
+    set_log_mode(new.as_int());
+}
+
+/// Opens the log file if the `logfile` Dvar is set, and starts following
+/// changes to it.
+///
+/// The log file lives in the game directory, so this has to wait until the
+/// filesystem is up.
+pub(super) fn init() {
+    dvar::add_change_callback("logfile", logfile_changed);
+    set_log_mode(dvar::get_int("logfile"));
+}
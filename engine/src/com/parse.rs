@@ -0,0 +1,569 @@
+// This file implements the text parser shared by everything that reads
+// hand-written data files (menus, string tables, arena files, configs,
+// etc.).
+//
+// Parsing happens in sessions, opened with `begin_parsing` and closed with
+// `end_parsing`. Sessions nest (e.g. a menu file including another), and
+// each thread has its own stack of them, so the settings of one file (CSV
+// mode, negative numbers, ...) never leak into another. The text itself is
+// owned by the caller, which passes a cursor (`&mut &str`) that the parser
+// advances past each token.
+
+use core::{cell::RefCell, fmt::Display};
+
+use arrayvec::ArrayVec;
+
+use crate::*;
+
+/// Maximum number of nested parse sessions per thread.
+pub const MAX_PARSE_SESSIONS: usize = 16;
+
+/// What kind of token was last returned by [`parse`] or [`parse_on_line`].
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ParseTokenType {
+    UNKNOWN,
+    NUMBER,
+    STRING,
+    NAME,
+    HASH,
+    PUNCTUATION,
+}
+
+// Multi-character punctuation, recognized when the session isn't space
+// delimited. Longer sequences come first so they match before their
+// prefixes.
+const PUNCTUATION: [&str; 22] = [
+    ">>=", "<<=", "...", "+=", "-=", "*=", "/=", "&=", "|=", "^=", "==", "!=",
+    "<=", ">=", "&&", "||", "++", "--", "->", "<<", ">>", "::",
+];
+
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Debug)]
+struct ParseInfo {
+    token: String,
+    token_type: ParseTokenType,
+    lines: i32,
+    unget_token: bool,
+    space_delimited: bool,
+    keep_string_quotes: bool,
+    csv: bool,
+    // Set when a CSV field ended with a comma, so there's (at least) one
+    // more field on the line, even if it's empty
+    csv_field_pending: bool,
+    negative_numbers: bool,
+    error_prefix: String,
+    warning_prefix: String,
+    parse_file: String,
+}
+
+impl ParseInfo {
+    fn new(parse_file: &str) -> Self {
+        Self {
+            token: String::new(),
+            token_type: ParseTokenType::UNKNOWN,
+            lines: 1,
+            unget_token: false,
+            space_delimited: true,
+            keep_string_quotes: false,
+            csv: false,
+            csv_field_pending: false,
+            negative_numbers: false,
+            error_prefix: String::from("ERROR: "),
+            warning_prefix: String::from("WARNING: "),
+            parse_file: parse_file.to_owned(),
+        }
+    }
+}
+
+struct ParseThreadInfo {
+    parse_info: ArrayVec<ParseInfo, MAX_PARSE_SESSIONS>,
+    // Used when parsing outside of any session
+    default_info: ParseInfo,
+}
+
+impl ParseThreadInfo {
+    fn new() -> Self {
+        Self {
+            parse_info: ArrayVec::new(),
+            default_info: ParseInfo::new(""),
+        }
+    }
+}
+
+thread_local! {
+    static G_PARSE: RefCell<ParseThreadInfo> =
+        RefCell::new(ParseThreadInfo::new());
+}
+
+// Runs `f` on the innermost parse session of the current thread
+fn with_parse_info<R>(f: impl FnOnce(&mut ParseInfo) -> R) -> R {
+    G_PARSE.with(|parse| {
+        let mut parse = parse.borrow_mut();
+        let parse = &mut *parse;
+        match parse.parse_info.last_mut() {
+            Some(pi) => f(pi),
+            None => f(&mut parse.default_info),
+        }
+    })
+}
+
+/// Starts a new parse session for the file `filename` (which is only used
+/// in error messages), nested inside of the current one. Settings start out
+/// at their defaults: space delimited, no CSV, quotes stripped, and no
+/// negative numbers.
+///
+/// Every call must be paired with a call to [`end_parsing`].
+pub fn begin_parsing(filename: &str) {
+    let full = G_PARSE.with(|parse| {
+        parse
+            .borrow_mut()
+            .parse_info
+            .try_push(ParseInfo::new(filename))
+            .is_err()
+    });
+
+    if full {
+        com::errorln!(
+            com::ErrorParm::FATAL,
+            "com::begin_parsing: session overflow trying to parse {}",
+            filename
+        );
+    }
+}
+
+/// Ends the parse session started by the last call to [`begin_parsing`].
+pub fn end_parsing() {
+    let empty =
+        G_PARSE.with(|parse| parse.borrow_mut().parse_info.pop().is_none());
+
+    if empty {
+        com::errorln!(
+            com::ErrorParm::FATAL,
+            "com::end_parsing: session underflow"
+        );
+    }
+}
+
+/// Ends every parse session on the current thread.
+///
+/// Used after an error unwinds out of whatever was parsing, since it didn't
+/// get the chance to call [`end_parsing`].
+pub(super) fn reset_sessions() {
+    G_PARSE.with(|parse| parse.borrow_mut().parse_info.clear());
+}
+
+/// Sets whether tokens are delimited only by whitespace (the default), or
+/// whether names, numbers, and punctuation are split into separate tokens
+/// (e.g. `a+=1` is one token or three).
+pub fn set_space_delimited(space_delimited: bool) {
+    with_parse_info(|pi| pi.space_delimited = space_delimited);
+}
+
+/// Sets whether quoted strings keep their quotes when returned as tokens.
+pub fn set_keep_string_quotes(keep_string_quotes: bool) {
+    with_parse_info(|pi| pi.keep_string_quotes = keep_string_quotes);
+}
+
+/// Sets whether the text is comma-separated values, where every field up
+/// to the next comma or newline is a token (even if it's empty), and quoted
+/// fields use `""` for a literal quote.
+pub fn set_csv(csv: bool) {
+    with_parse_info(|pi| pi.csv = csv);
+}
+
+/// Sets whether a '-' directly in front of a number is part of the number,
+/// or a separate punctuation token. Only matters when the session isn't
+/// space delimited.
+pub fn set_parse_negative_numbers(negative_numbers: bool) {
+    with_parse_info(|pi| pi.negative_numbers = negative_numbers);
+}
+
+/// Sets the text printed in front of the messages of [`parse_error`].
+pub fn set_error_prefix(prefix: &str) {
+    with_parse_info(|pi| pi.error_prefix = prefix.to_owned());
+}
+
+/// Sets the text printed in front of the messages of [`parse_warning`].
+pub fn set_warning_prefix(prefix: &str) {
+    with_parse_info(|pi| pi.warning_prefix = prefix.to_owned());
+}
+
+/// Returns the line the parser is on in the current session (starting at
+/// 1).
+pub fn current_parse_line() -> i32 {
+    with_parse_info(|pi| pi.lines)
+}
+
+/// Resets the line count of the current session, e.g. when starting over
+/// on the same text.
+pub fn reset_parse_lines() {
+    with_parse_info(|pi| pi.lines = 1);
+}
+
+/// Returns the type of the last token parsed.
+pub fn token_type() -> ParseTokenType {
+    with_parse_info(|pi| pi.token_type)
+}
+
+/// Makes the next call to [`parse`] or [`parse_on_line`] return the last
+/// token again instead of parsing a new one.
+pub fn unget_token() {
+    with_parse_info(|pi| {
+        if pi.unget_token {
+            parse_warning_internal(pi, "unget_token called twice");
+        }
+        pi.unget_token = true;
+    });
+}
+
+fn location(pi: &ParseInfo) -> String {
+    if pi.parse_file.is_empty() {
+        format!("line {}", pi.lines)
+    } else {
+        format!("\'{}\', line {}", pi.parse_file, pi.lines)
+    }
+}
+
+fn parse_warning_internal(pi: &ParseInfo, msg: impl Display) {
+    com::warnln!(
+        console::Channel::PARSER_SCRIPT,
+        "{}{}: {}",
+        pi.warning_prefix,
+        location(pi),
+        msg
+    );
+}
+
+/// Prints `msg` as an error, prefixed with the error prefix (see
+/// [`set_error_prefix`]) and the current file and line.
+pub fn parse_error(msg: impl Display) {
+    with_parse_info(|pi| {
+        com::print_errorln!(
+            console::Channel::PARSER_SCRIPT,
+            "{}{}: {}",
+            pi.error_prefix,
+            location(pi),
+            msg
+        );
+    });
+}
+
+/// Prints `msg` as a warning, prefixed with the warning prefix (see
+/// [`set_warning_prefix`]) and the current file and line.
+pub fn parse_warning(msg: impl Display) {
+    with_parse_info(|pi| parse_warning_internal(pi, msg));
+}
+
+// Skips whitespace and comments. Returns false if a newline was skipped
+// and line breaks aren't allowed, leaving `data` at the newline.
+fn skip_whitespace(
+    pi: &mut ParseInfo,
+    data: &mut &str,
+    allow_line_breaks: bool,
+) -> bool {
+    loop {
+        let trimmed = data.trim_start_matches(|c: char| {
+            c.is_whitespace() && (c != '\n' || allow_line_breaks)
+        });
+        pi.lines += data
+            .get(..data.len() - trimmed.len())
+            .unwrap_or_default()
+            .matches('\n')
+            .count() as i32;
+        *data = trimmed;
+
+        if data.starts_with('\n') {
+            // Only reached if line breaks aren't allowed
+            return false;
+        } else if let Some(rest) = data.strip_prefix("//") {
+            *data = rest.find('\n').map_or("", |i| rest.get(i..).unwrap());
+        } else if let Some(rest) = data.strip_prefix("/*") {
+            let end = rest.find("*/").map_or(rest.len(), |i| i + 2);
+            let comment = rest.get(..end).unwrap();
+            if !allow_line_breaks && comment.contains('\n') {
+                // Treat a multi-line comment like the newline it contains
+                *data = rest.get(end..).unwrap();
+                pi.lines += comment.matches('\n').count() as i32;
+                return false;
+            }
+            pi.lines += comment.matches('\n').count() as i32;
+            *data = rest.get(end..).unwrap();
+        } else {
+            return true;
+        }
+    }
+}
+
+// Parses a quoted string starting at the opening quote. CSV strings use ""
+// for a literal quote
+fn parse_quoted(pi: &mut ParseInfo, data: &mut &str) -> String {
+    let mut token = String::new();
+    if pi.keep_string_quotes {
+        token.push('"');
+    }
+
+    let mut chars = data.char_indices().skip(1).peekable();
+    let mut end = data.len();
+    while let Some((i, c)) = chars.next() {
+        if c == '"' {
+            if pi.csv && chars.peek().map(|&(_, c)| c) == Some('"') {
+                chars.next();
+                token.push('"');
+                continue;
+            }
+            if pi.keep_string_quotes {
+                token.push('"');
+            }
+            end = i + 1;
+            break;
+        }
+
+        if c == '\n' {
+            pi.lines += 1;
+        }
+        token.push(c);
+    }
+
+    *data = data.get(end..).unwrap_or_default();
+    token
+}
+
+// Parses a single CSV field, up to (and consuming) the next comma, or up to
+// (but not consuming) the next newline
+fn parse_csv_field(pi: &mut ParseInfo, data: &mut &str) -> String {
+    *data = data.trim_start_matches([' ', '\t', '\r']);
+    let token = if data.starts_with('"') {
+        let token = parse_quoted(pi, data);
+        // Ignore anything between the closing quote and the separator
+        let end = data.find([',', '\n']).unwrap_or(data.len());
+        *data = data.get(end..).unwrap();
+        token
+    } else {
+        let end = data.find([',', '\n']).unwrap_or(data.len());
+        let token = data.get(..end).unwrap().trim_end().to_owned();
+        *data = data.get(end..).unwrap();
+        token
+    };
+
+    if let Some(rest) = data.strip_prefix(',') {
+        *data = rest;
+        pi.csv_field_pending = true;
+    }
+    token
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.' || c == '/' || c == '\\'
+}
+
+// Parses a name, number, or punctuation token (i.e. when the session isn't
+// space delimited)
+fn parse_split(pi: &mut ParseInfo, data: &mut &str) -> String {
+    let starts_number = |s: &str| {
+        let mut chars = s.chars();
+        match chars.next() {
+            Some(c) if c.is_ascii_digit() => true,
+            Some('.') => chars.next().is_some_and(|c| c.is_ascii_digit()),
+            _ => false,
+        }
+    };
+
+    let negative = pi.negative_numbers
+        && data.strip_prefix('-').is_some_and(starts_number);
+    // Multi-character punctuation has to be matched before names, since
+    // names can contain '.' and '/' (e.g. "..." or "/=")
+    let punctuation = PUNCTUATION.iter().find(|p| data.starts_with(*p));
+    let (end, token_type) = if negative || starts_number(data) {
+        let skip = usize::from(negative);
+        let end = data
+            .get(skip..)
+            .unwrap()
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '.'))
+            .map_or(data.len(), |i| i + skip);
+        (end, ParseTokenType::NUMBER)
+    } else if let Some(p) = punctuation {
+        (p.len(), ParseTokenType::PUNCTUATION)
+    } else if data.starts_with(is_name_char) {
+        let end = data.find(|c| !is_name_char(c)).unwrap_or(data.len());
+        (end, ParseTokenType::NAME)
+    } else {
+        let end = data.chars().next().map_or(0, char::len_utf8);
+        (end, ParseTokenType::PUNCTUATION)
+    };
+
+    pi.token_type = token_type;
+    let token = data.get(..end).unwrap().to_owned();
+    *data = data.get(end..).unwrap();
+    token
+}
+
+fn parse_ext(data: &mut &str, allow_line_breaks: bool) -> Option<String> {
+    with_parse_info(|pi| {
+        if pi.unget_token {
+            pi.unget_token = false;
+            return Some(pi.token.clone());
+        }
+
+        pi.token.clear();
+        pi.token_type = ParseTokenType::UNKNOWN;
+
+        if pi.csv {
+            // In CSV mode every field is a token, even an empty one, so only
+            // the end of the line (or text) ends a line. A comma right before
+            // it still leaves one empty field
+            let at_end = data.is_empty() || data.starts_with('\n');
+            if core::mem::take(&mut pi.csv_field_pending) && at_end {
+                pi.token_type = ParseTokenType::STRING;
+                return Some(String::new());
+            }
+            if data.is_empty() || (!allow_line_breaks && data.starts_with('\n'))
+            {
+                return None;
+            }
+            if let Some(rest) = data.strip_prefix('\n') {
+                pi.lines += 1;
+                *data = rest;
+                if data.is_empty() {
+                    return None;
+                }
+            }
+            pi.token = parse_csv_field(pi, data);
+            pi.token_type = ParseTokenType::STRING;
+            return Some(pi.token.clone());
+        }
+
+        if !skip_whitespace(pi, data, allow_line_breaks) || data.is_empty() {
+            return None;
+        }
+
+        pi.token = if data.starts_with('"') {
+            pi.token_type = ParseTokenType::STRING;
+            parse_quoted(pi, data)
+        } else if pi.space_delimited {
+            let end = data.find(char::is_whitespace).unwrap_or(data.len());
+            let token = data.get(..end).unwrap().to_owned();
+            *data = data.get(end..).unwrap();
+            pi.token_type = if token.starts_with('#') {
+                ParseTokenType::HASH
+            } else if token.parse::<f64>().is_ok() {
+                ParseTokenType::NUMBER
+            } else if !token.contains(is_name_char) {
+                ParseTokenType::PUNCTUATION
+            } else {
+                ParseTokenType::NAME
+            };
+            token
+        } else if let Some(rest) = data.strip_prefix('#') {
+            // Directives like #include are a single token
+            let end = rest.find(|c| !is_name_char(c)).unwrap_or(rest.len());
+            let token = data.get(..=end).unwrap().to_owned();
+            *data = rest.get(end..).unwrap();
+            pi.token_type = ParseTokenType::HASH;
+            token
+        } else {
+            parse_split(pi, data)
+        };
+
+        Some(pi.token.clone())
+    })
+}
+
+/// Parses the next token from `data` and advances `data` past it, moving on
+/// to the next line if necessary.
+///
+/// Returns [`None`] once the end of `data` is reached.
+///
+/// # Example
+/// ```
+/// let mut data = "menuDef { name \"main\" }";
+/// com::begin_parsing("main.menu");
+/// while let Some(token) = com::parse(&mut data) {
+///     // ...
+/// }
+/// com::end_parsing();
+/// ```
+pub fn parse(data: &mut &str) -> Option<String> {
+    parse_ext(data, true)
+}
+
+/// Like [`parse`], but doesn't move on to the next line. Returns [`None`]
+/// at the end of the current line.
+pub fn parse_on_line(data: &mut &str) -> Option<String> {
+    parse_ext(data, false)
+}
+
+/// Skips the rest of the current line, including the newline.
+pub fn skip_rest_of_line(data: &mut &str) {
+    with_parse_info(|pi| {
+        pi.unget_token = false;
+        match data.find('\n') {
+            Some(i) => {
+                pi.lines += 1;
+                *data = data.get(i + 1..).unwrap();
+            }
+            None => *data = "",
+        }
+    });
+}
+
+/// Parses the next token and checks that it's `expected`, printing an
+/// error (or a warning, if `warning` is `true`) if it isn't.
+///
+/// Returns `true` if the token matched.
+pub fn match_token(data: &mut &str, expected: &str, warning: bool) -> bool {
+    let token = parse(data).unwrap_or_default();
+    if token == expected {
+        return true;
+    }
+
+    let msg = format!("expected \'{}\', found \'{}\'", expected, token);
+    if warning {
+        parse_warning(msg);
+    } else {
+        parse_error(msg);
+    }
+    false
+}
+
+/// Parses the next token as an [`i32`], returning 0 (with a warning) if it
+/// isn't one.
+pub fn parse_int(data: &mut &str) -> i32 {
+    let token = parse(data).unwrap_or_default();
+    token.parse::<i32>().unwrap_or_else(|_| {
+        parse_warning(format!("expected an integer, found \'{}\'", token));
+        0
+    })
+}
+
+/// Parses the next token as an [`f32`], returning 0.0 (with a warning) if it
+/// isn't one.
+pub fn parse_float(data: &mut &str) -> f32 {
+    let token = parse(data).unwrap_or_default();
+    token.parse::<f32>().unwrap_or_else(|_| {
+        parse_warning(format!("expected a number, found \'{}\'", token));
+        0.0
+    })
+}
+
+/// Skips tokens until the braces opened so far are all closed. `depth` is
+/// the number of braces already open (i.e. 1 if the opening brace has
+/// already been parsed, 0 if it hasn't).
+///
+/// Returns `false` if the end of `data` was reached first.
+pub fn skip_braced_section(data: &mut &str, mut depth: usize) -> bool {
+    while let Some(token) = parse(data) {
+        match token.as_str() {
+            "{" => depth += 1,
+            "}" => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    return true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    false
+}
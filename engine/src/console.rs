@@ -88,9 +88,45 @@ pub enum Channel {
 }
 
 impl Channel {
+    const ALL: [Self; 20] = [
+        Self::DONT_FILTER,
+        Self::ERROR,
+        Self::GAMENOTIFY,
+        Self::BOLDGAME,
+        Self::SUBTITLE,
+        Self::LOGFILEONLY,
+        Self::GFX,
+        Self::SOUND,
+        Self::FILES,
+        Self::DEVGUI,
+        Self::PROFILE,
+        Self::UI,
+        Self::CLIENT,
+        Self::SERVER,
+        Self::SYSTEM,
+        Self::ANIM,
+        Self::FX,
+        Self::LIVE,
+        Self::PARSER_SCRIPT,
+        Self::TASK,
+    ];
+
     const fn as_i32(self) -> i32 {
         self as _
     }
+
+    /// Looks up a channel by its number or (case-insensitive) name, e.g.
+    /// `"8"` or `"files"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        if let Ok(i) = name.parse::<usize>() {
+            return Self::ALL.get(i).copied();
+        }
+
+        Self::ALL
+            .iter()
+            .find(|c| format!("{:?}", c).eq_ignore_ascii_case(name))
+            .copied()
+    }
 }
 
 #[allow(clippy::indexing_slicing)]
@@ -7,13 +7,27 @@
 )]
 
 mod builder;
+mod callbacks;
+mod export;
+mod handle;
 mod limits;
+mod save;
 mod value;
 
+pub use callbacks::{
+    add_change_callback, add_flags_change_callback, remove_change_callback,
+    ChangeCallback, ChangeCallbackId,
+};
+pub use export::{export, import, ExportFormat};
+pub use handle::{handle, DvarHandle, HandleType};
+pub use save::{load_saved_dvars, write_saved_dvars, DvarSnapshot};
+pub use value::DvarValue;
+
 pub mod global_fns;
 pub use global_fns::*;
 
 mod cmds;
+pub use cmds::{command, describe};
 
 /// This file contains all of code related to the Dvar subsystem, including
 /// the [`Dvar`] itself, functions to get, set, and create Dvars, and
@@ -28,10 +42,10 @@ use std::sync::RwLock;
 extern crate alloc;
 use alloc::sync::Arc;
 
-use self::{limits::DvarLimits, value::DvarValue};
+use self::limits::DvarLimits;
 
 // Enum for the possible sources a Dvar may be set from
-#[derive(PartialEq, Eq)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum SetSource {
     Internal,
     External,
@@ -271,6 +285,7 @@ impl Dvar {
 
     fn clamp_current_value_to_domain(&mut self) {
         Self::clamp_value_to_domain(&mut self.current, &self.domain);
+        handle::sync(&self.name, &self.current);
     }
 
     fn clamp_latched_value_to_domain(&mut self) {
@@ -319,8 +334,10 @@ impl Dvar {
             return false;
         }
 
-        if self.flags.contains(DvarFlags::CHEAT_PROTECTED)
-            && (find("sv_cheats").unwrap().current.as_bool().unwrap() == false)
+        // Can't look up sv_cheats here, since the lock for DVARS is usually
+        // held by the caller
+        if !self.flags.contains(DvarFlags::CHEAT_PROTECTED)
+            || CHEATS_ENABLED.load(Ordering::SeqCst) == true
         {
             true
         } else {
@@ -369,6 +386,7 @@ impl Dvar {
 
         if value != self.current {
             self.current = value;
+            handle::sync(&self.name, &self.current);
             self.modified = true;
         } else {
             self.latched = value;
@@ -458,7 +476,7 @@ impl Dvar {
 
         if com::log_file_open() && self.current != value {
             com::println!(
-                console::Channel::SYSTEM,
+                console::Channel::LOGFILEONLY,
                 "      dvar set {} {}",
                 self.name,
                 self.current,
@@ -480,9 +498,11 @@ impl Dvar {
         }
 
         if source == SetSource::External || source == SetSource::Script {
-            if self.can_change_value(&value, source)
-                && self.flags.contains(DvarFlags::LATCHED)
-            {
+            if !self.can_change_value(&value, source) {
+                return;
+            }
+
+            if self.flags.contains(DvarFlags::LATCHED) {
                 self.latched = value;
                 if self.latched != self.current {
                     com::println!(
@@ -491,8 +511,8 @@ impl Dvar {
                         self.name,
                     );
                 }
+                return;
             }
-            return;
         } else if source == SetSource::Devgui
             && self.flags.contains(DvarFlags::ALLOW_SET_FROM_DEVGUI)
         {
@@ -506,13 +526,110 @@ impl Dvar {
                 .write()
                 .unwrap()
                 .insert(modified_flags | self.flags);
-            self.current = value;
+            let old = core::mem::replace(&mut self.current, value);
+            handle::sync(&self.name, &self.current);
+            callbacks::queue_change(
+                &self.name,
+                self.flags,
+                old,
+                self.current.clone(),
+                source,
+            );
             self.modified = true;
         } else {
             self.latched = self.current.clone();
         }
     }
 
+    // Parse a string (e.g., one typed into the console) into a value of the
+    // same type as the Dvar's current value. Vectors and colors accept their
+    // components separated by whitespace and/or commas, optionally wrapped
+    // in parentheses (i.e., the format DvarValue's Display impl produces).
+    // Enumerations accept a string from their domain or an index into it
+    fn value_from_string(&self, s: &str) -> Option<DvarValue> {
+        let s = s.trim();
+        let floats = || {
+            s.trim_start_matches('(')
+                .trim_end_matches(')')
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|t| !t.is_empty())
+                .map(str::parse::<f32>)
+                .collect::<Result<Vec<_>, _>>()
+                .ok()
+        };
+
+        match self.current {
+            DvarValue::Bool(_) => match s.to_lowercase().as_str() {
+                "true" => Some(DvarValue::Bool(true)),
+                "false" => Some(DvarValue::Bool(false)),
+                _ => s.parse::<i32>().ok().map(|i| DvarValue::Bool(i != 0)),
+            },
+            DvarValue::Float(_) => s.parse().ok().map(DvarValue::Float),
+            DvarValue::Vector2(_) => match *floats()? {
+                [x, y] => Some(DvarValue::Vector2((x, y))),
+                _ => None,
+            },
+            DvarValue::Vector3(_) => match *floats()? {
+                [x, y, z] => Some(DvarValue::Vector3((x, y, z))),
+                _ => None,
+            },
+            DvarValue::Vector4(_) => match *floats()? {
+                [x, y, z, w] => Some(DvarValue::Vector4((x, y, z, w))),
+                _ => None,
+            },
+            DvarValue::Int(_) => s.parse().ok().map(DvarValue::Int),
+            DvarValue::String(_) => Some(DvarValue::String(s.to_owned())),
+            DvarValue::Enumeration(_) => self
+                .domain
+                .as_enumeration_limits()?
+                .resolve(s)
+                .map(|e| DvarValue::Enumeration(e.clone())),
+            DvarValue::Color(_) => match *floats()? {
+                [r, g, b] => Some(DvarValue::Color((r, g, b, 1.0))),
+                [r, g, b, a] => Some(DvarValue::Color((r, g, b, a))),
+                _ => None,
+            },
+            DvarValue::Int64(_) => s.parse().ok().map(DvarValue::Int64),
+            DvarValue::LinearColorRGB(_) => match *floats()? {
+                [r, g, b] => Some(DvarValue::LinearColorRGB((r, g, b))),
+                _ => None,
+            },
+            DvarValue::ColorXYZ(_) => match *floats()? {
+                [x, y, z] => Some(DvarValue::ColorXYZ((x, y, z))),
+                _ => None,
+            },
+        }
+    }
+
+    // Check that all of the Dvar's values are the same type as its domain
+    fn values_match_domain_type(&self) -> bool {
+        [&self.current, &self.latched, &self.reset, &self.saved]
+            .iter()
+            .all(|v| {
+                matches!(
+                    (&self.domain, v),
+                    (DvarLimits::Bool(_), DvarValue::Bool(_))
+                        | (DvarLimits::Float(_), DvarValue::Float(_))
+                        | (DvarLimits::Vector2(_), DvarValue::Vector2(_))
+                        | (DvarLimits::Vector3(_), DvarValue::Vector3(_))
+                        | (DvarLimits::Vector4(_), DvarValue::Vector4(_))
+                        | (DvarLimits::Int(_), DvarValue::Int(_))
+                        | (DvarLimits::String(_), DvarValue::String(_))
+                        | (
+                            DvarLimits::Enumeration(_),
+                            DvarValue::Enumeration(_)
+                        )
+                        | (DvarLimits::Color(_), DvarValue::Color(_))
+                        | (DvarLimits::Int64(_), DvarValue::Int64(_))
+                        | (
+                            DvarLimits::LinearColorRGB(_),
+                            DvarValue::LinearColorRGB(_)
+                        )
+                        | (DvarLimits::ColorXYZ(_), DvarValue::ColorXYZ(_))
+                )
+            })
+    }
+
     pub fn make_latched_value_current(&mut self) {
         self.set_variant(self.latched.clone(), SetSource::Internal);
     }
@@ -526,6 +643,72 @@ lazy_static! {
     static ref IS_DVAR_SYSTEM_ACTIVE: AtomicBool = AtomicBool::new(false);
 }
 
+// Mirrors the value of sv_cheats, kept up to date by sv_cheats_changed
+static CHEATS_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Resets every cheat-protected [`Dvar`] to its reset value, and prints a
+/// report of each one that changed.
+///
+/// Called automatically whenever `sv_cheats` is turned off.
+///
+/// # Return Value
+///
+/// Returns the number of [`Dvar`]s that changed.
+///
+/// # Panics
+/// Panics if the write lock for [`DVARS`] can't be acquired (usually because
+/// the write lock or a read lock is held by a function farther up the
+/// call stack).
+pub fn reset_cheat_dvars() -> usize {
+    let mut changed = Vec::new();
+    {
+        let mut writer = DVARS.write().unwrap();
+        for d in writer.values_mut() {
+            if d.flags.contains(DvarFlags::CHEAT_PROTECTED)
+                && d.current != d.reset
+            {
+                let old = d.current.clone();
+                d.reset(SetSource::Internal);
+                changed.push((d.name.clone(), old, d.current.clone()));
+            }
+        }
+    }
+    callbacks::run_change_callbacks();
+
+    if !changed.is_empty() {
+        changed.sort_by(|a, b| a.0.cmp(&b.0));
+        com::println!(
+            console::Channel::SYSTEM,
+            "Cheats disabled, reset {} cheat-protected dvars:",
+            changed.len()
+        );
+        for (name, old, new) in &changed {
+            com::println!(
+                console::Channel::SYSTEM,
+                "    {}: \"{}\" -> \"{}\"",
+                name,
+                old,
+                new
+            );
+        }
+    }
+
+    changed.len()
+}
+
+fn sv_cheats_changed(
+    _name: &str,
+    _old: &DvarValue,
+    new: &DvarValue,
+    _source: SetSource,
+) {
+    let enabled = new.as_bool().unwrap_or(false);
+    CHEATS_ENABLED.store(enabled, Ordering::SeqCst);
+    if !enabled {
+        reset_cheat_dvars();
+    }
+}
+
 /// Initializes the Dvar subsystem
 ///
 /// Shouldn't ever be called more than once, but doing so
@@ -547,6 +730,7 @@ pub fn init() {
             Some("External Dvar"),
         )
         .unwrap();
+        add_change_callback("sv_cheats", sv_cheats_changed);
         self::cmds::add_commands();
     }
 }
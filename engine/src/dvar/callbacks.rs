@@ -0,0 +1,174 @@
+// This file implements change callbacks for Dvars, so that other subsystems
+// can react to a Dvar's value changing instead of polling its `modified`
+// flag.
+//
+// Dvars are changed while the write lock for DVARS is held, and callbacks
+// will usually want to read Dvars themselves, so they can't be run right
+// away. Instead, `Dvar::set_variant` queues each change with `queue_change`,
+// and the public functions that change Dvars call `run_change_callbacks`
+// once they've released the lock.
+
+use std::sync::{Mutex, RwLock};
+
+use lazy_static::lazy_static;
+
+use super::{value::DvarValue, DvarFlags, SetSource};
+
+/// Function called when a [`Dvar`]'s current value changes.
+///
+/// Receives the name of the [`Dvar`], its old value, its new value, and the
+/// [`SetSource`] the change came from.
+pub type ChangeCallback = fn(&str, &DvarValue, &DvarValue, SetSource);
+
+/// Opaque identifier for a registered [`ChangeCallback`], used to remove it
+/// with [`remove_change_callback`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct ChangeCallbackId(usize);
+
+/// What a [`ChangeCallback`] is registered for.
+enum Trigger {
+    /// The [`Dvar`] with this name.
+    Name(String),
+    /// Every [`Dvar`] with at least one of these flags set.
+    Flags(DvarFlags),
+}
+
+struct Change {
+    name: String,
+    flags: DvarFlags,
+    old: DvarValue,
+    new: DvarValue,
+    source: SetSource,
+}
+
+type CallbackEntry = (ChangeCallbackId, Trigger, ChangeCallback);
+
+lazy_static! {
+    static ref CALLBACKS: RwLock<Vec<CallbackEntry>> = RwLock::new(Vec::new());
+    static ref NEXT_CALLBACK_ID: Mutex<usize> = Mutex::new(0);
+    static ref PENDING_CHANGES: Mutex<Vec<Change>> = Mutex::new(Vec::new());
+}
+
+fn add_callback(
+    trigger: Trigger,
+    callback: ChangeCallback,
+) -> ChangeCallbackId {
+    let id = {
+        let mut next = NEXT_CALLBACK_ID.lock().unwrap();
+        *next += 1;
+        ChangeCallbackId(*next)
+    };
+
+    CALLBACKS.write().unwrap().push((id, trigger, callback));
+    id
+}
+
+/// Registers `callback` to be called whenever the current value of the
+/// [`Dvar`] named `name` changes.
+///
+/// The [`Dvar`] doesn't need to exist yet.
+///
+/// # Return Value
+///
+/// Returns an id that can be passed to [`remove_change_callback`].
+///
+/// # Example
+/// ```
+/// fn on_change(name: &str, old: &DvarValue, new: &DvarValue, _: SetSource) {
+///     println!("{} changed from {} to {}", name, old, new);
+/// }
+/// add_change_callback("in_mouse", on_change);
+/// ```
+pub fn add_change_callback(
+    name: &str,
+    callback: ChangeCallback,
+) -> ChangeCallbackId {
+    add_callback(Trigger::Name(name.to_owned()), callback)
+}
+
+/// Registers `callback` to be called whenever the current value of any
+/// [`Dvar`] with at least one of `flags` set changes.
+///
+/// # Return Value
+///
+/// Returns an id that can be passed to [`remove_change_callback`].
+///
+/// # Example
+/// ```
+/// fn on_change(name: &str, _: &DvarValue, _: &DvarValue, _: SetSource) {
+///     println!("archived dvar {} changed", name);
+/// }
+/// add_flags_change_callback(DvarFlags::ARCHIVE, on_change);
+/// ```
+pub fn add_flags_change_callback(
+    flags: DvarFlags,
+    callback: ChangeCallback,
+) -> ChangeCallbackId {
+    add_callback(Trigger::Flags(flags), callback)
+}
+
+/// Unregisters a callback previously registered with
+/// [`add_change_callback`] or [`add_flags_change_callback`].
+///
+/// # Return Value
+///
+/// Returns [`Err`] if no callback with `id` is registered.
+pub fn remove_change_callback(id: ChangeCallbackId) -> Result<(), ()> {
+    let mut callbacks = CALLBACKS.write().unwrap();
+    let len = callbacks.len();
+    callbacks.retain(|(i, _, _)| *i != id);
+    if callbacks.len() == len {
+        Err(())
+    } else {
+        Ok(())
+    }
+}
+
+/// Records a change to a [`Dvar`]'s current value, to be passed to the
+/// matching callbacks by the next call to [`run_change_callbacks`].
+pub(super) fn queue_change(
+    name: &str,
+    flags: DvarFlags,
+    old: DvarValue,
+    new: DvarValue,
+    source: SetSource,
+) {
+    if CALLBACKS.read().unwrap().is_empty() {
+        return;
+    }
+
+    PENDING_CHANGES.lock().unwrap().push(Change {
+        name: name.to_owned(),
+        flags,
+        old,
+        new,
+        source,
+    });
+}
+
+/// Runs the callbacks for every change queued since the last call.
+///
+/// Must not be called while the lock for [`DVARS`](super::DVARS) is held,
+/// since callbacks are free to get and set [`Dvar`]s.
+pub(super) fn run_change_callbacks() {
+    // Take the queue first so that callbacks which change other Dvars can
+    // queue (and run) their own changes
+    let changes = core::mem::take(&mut *PENDING_CHANGES.lock().unwrap());
+
+    for change in changes {
+        let callbacks = CALLBACKS
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, trigger, _)| match trigger {
+                Trigger::Name(name) => *name == change.name,
+                Trigger::Flags(flags) => change.flags.intersects(*flags),
+            })
+            .map(|(_, _, callback)| *callback)
+            .collect::<Vec<_>>();
+
+        for callback in callbacks {
+            callback(&change.name, &change.old, &change.new, change.source);
+        }
+    }
+}
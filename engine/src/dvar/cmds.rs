@@ -1,15 +1,25 @@
-use core::sync::atomic::{AtomicBool, AtomicIsize, Ordering};
+use core::{
+    fmt::Display,
+    sync::atomic::{AtomicBool, AtomicIsize, Ordering},
+};
+use std::path::PathBuf;
 
 use crate::*;
 
 use super::{
-    add_flags, get_bool, get_enumeration,
+    add_flags,
+    callbacks::run_change_callbacks,
+    export, get_bool, get_enumeration,
     global_fns::{exists, find},
-    name_is_valid, register_bool, register_color, register_float, register_int,
-    set_bool_from_source, set_float_from_source, set_int64_from_source,
-    set_int_from_source, set_string_from_source,
+    import,
+    limits::DvarLimitsEnumeration,
+    name_is_valid, register_bool, register_color, register_enumeration,
+    register_float, register_int, register_int64, register_string,
+    register_vector2, register_vector3, register_vector4, set_bool_from_source,
+    set_float_from_source, set_from_string_from_source, set_int64_from_source,
+    set_int_from_source,
     value::DvarValue,
-    Dvar, DvarFlags, SetSource, DVARS,
+    Dvar, DvarFlags, ExportFormat, SetSource, DVARS,
 };
 
 use lazy_static::lazy_static;
@@ -223,7 +233,22 @@ lazy_static! {
 }
 
 fn set_command(name: &str, value: &str) {
-    if set_string_from_source(name, value, SetSource::External).is_err() {
+    if !exists(name) {
+        // Hold on to the value in an external Dvar until something
+        // registers the real one (see insert_registered)
+        if register_string(
+            name,
+            value,
+            DvarFlags::EXTERNAL,
+            Some("External Dvar"),
+        )
+        .is_err()
+        {
+            return;
+        }
+    } else if set_from_string_from_source(name, value, SetSource::External)
+        .is_err()
+    {
         return;
     }
 
@@ -242,13 +267,62 @@ fn get_combined_string(start_idx: usize) -> String {
     for i in start_idx..argc {
         let argv_i = cmd::argv(i);
         str.push_str(&argv_i);
-        if argc != i - 1 {
+        if i != argc - 1 {
             str.push(' ');
         }
     }
     str
 }
 
+/// Handles a console command whose name didn't match any registered
+/// command by treating it as a [`Dvar`] name.
+///
+/// With no arguments, the [`Dvar`]'s current and default values are
+/// printed. Otherwise, the arguments are combined and the [`Dvar`] is set
+/// to the result, as if by `set`.
+///
+/// Returns `false` if no [`Dvar`] with the command's name exists.
+pub fn command() -> bool {
+    let name = cmd::argv(0);
+    let Some(dvar) = find(&name) else {
+        return false;
+    };
+
+    if cmd::argc() == 1 {
+        com::println!(
+            console::Channel::DONT_FILTER,
+            "\"{}\" is: \"{}^7\" default: \"{}^7\"",
+            dvar.name,
+            dvar.current,
+            dvar.reset,
+        );
+        return true;
+    }
+
+    set_command(&name, &get_combined_string(1));
+    true
+}
+
+/// Prints the current value, default value, and domain of the [`Dvar`]
+/// named `name`, as shown when its name is completed in the console.
+///
+/// Returns `false` if no [`Dvar`] with name `name` exists.
+pub fn describe(name: &str) -> bool {
+    let Some(dvar) = find(name) else {
+        return false;
+    };
+
+    com::println!(
+        console::Channel::DONT_FILTER,
+        "    \"{}\" is: \"{}^7\" default: \"{}^7\"",
+        dvar.name,
+        dvar.current,
+        dvar.reset,
+    );
+    com::println!(console::Channel::DONT_FILTER, "    {}", dvar.domain);
+    true
+}
+
 lazy_static! {
     static ref DVAR_COUNT_LOCAL: AtomicIsize = AtomicIsize::new(0);
 }
@@ -592,6 +666,8 @@ fn reset_f() {
     if exists(&name) {
         let mut writer = DVARS.write().unwrap();
         writer.get_mut(&name).unwrap().reset(SetSource::External);
+        drop(writer);
+        run_change_callbacks();
     }
 }
 
@@ -612,7 +688,29 @@ fn list_f() {
 }
 
 fn dump_f() {
-    com::dvar_dump(0, &cmd::argv(1));
+    if cmd::argc() > 3 {
+        com::println!(
+            console::Channel::DONT_FILTER,
+            "USAGE: dvardump [pattern] [channel]"
+        );
+        return;
+    }
+
+    let channel = if cmd::argc() == 3 {
+        let Some(channel) = console::Channel::from_name(&cmd::argv(2)) else {
+            com::println!(
+                console::Channel::DONT_FILTER,
+                "dvardump: unknown channel \'{}\'",
+                cmd::argv(2)
+            );
+            return;
+        };
+        channel
+    } else {
+        console::Channel::DONT_FILTER
+    };
+
+    com::dvar_dump(channel, &cmd::argv(1));
 }
 
 fn register_bool_f() {
@@ -857,6 +955,288 @@ fn register_color_f() {
     // Otherwise do nothing and continue
 }
 
+// Parses the bounds of a numeric domain from the arguments following a
+// command's default value. Accepts either a bare "<min> <max>" pair or the
+// text DvarLimits' Display impl produces (with or without the leading
+// "Domain is"), so that domains can be copied straight out of `dvardump`:
+//   "any number"                      -> (None, None)
+//   "any integer 5 or smaller"        -> (None, Some(5))
+//   "any 2D vector ... 0 or bigger"   -> (Some(0), None)
+//   "any number from 0 to 1"          -> (Some(0), Some(1))
+// No arguments at all means an unbounded domain. Returns None if the text
+// can't be parsed.
+#[allow(clippy::type_complexity)]
+fn parse_domain_bounds<T: core::str::FromStr>(
+    text: &str,
+) -> Option<(Option<T>, Option<T>)> {
+    let text = text.trim();
+    let text = text.strip_prefix("Domain is").unwrap_or(text);
+    let words = text.split_whitespace().collect::<Vec<_>>();
+    let number_after = |word: &str| {
+        let i = words.iter().position(|w| *w == word)?;
+        words.get(i + 1)?.parse::<T>().ok()
+    };
+    let number_before_or = || {
+        let i = words.iter().rposition(|w| *w == "or")?;
+        words.get(i.checked_sub(1)?)?.parse::<T>().ok()
+    };
+
+    if words.contains(&"from") && words.contains(&"to") {
+        Some((Some(number_after("from")?), Some(number_after("to")?)))
+    } else if words.ends_with(&["or", "smaller"]) {
+        Some((None, Some(number_before_or()?)))
+    } else if words.ends_with(&["or", "bigger"]) {
+        Some((Some(number_before_or()?), None))
+    } else if words.first().map_or(true, |w| *w == "any") {
+        Some((None, None))
+    } else if let [min, max] = words.as_slice() {
+        Some((Some(min.parse::<T>().ok()?), Some(max.parse::<T>().ok()?)))
+    } else {
+        None
+    }
+}
+
+// Shared by the dvar_* commands below. Registers the Dvar with `register`
+// if it doesn't exist yet, or if it exists as an external string Dvar
+// (i.e., it was created by `set` before anything registered it), in which
+// case the value that was set is kept if it can be converted to the new
+// type, along with any flags added by e.g. `seta`. Otherwise, the
+// existing Dvar is left alone, with a warning if its type doesn't match
+// `type_name`.
+fn register_external<T>(
+    name: &str,
+    type_name: &str,
+    register: impl FnOnce() -> Result<T, ()>,
+) {
+    let Some(d) = find(name) else {
+        // register_* already report their own errors
+        let _ = register();
+        return;
+    };
+
+    match d.current {
+        // Registering over an external string Dvar carries its value and
+        // flags over
+        DvarValue::String(_) if d.flags.contains(DvarFlags::EXTERNAL) => {
+            let _ = register();
+        }
+        ref v if v.type_name() == type_name => {}
+        _ => {
+            com::println!(
+                console::Channel::DONT_FILTER,
+                "dvar \'{}\' is not a {} dvar",
+                name,
+                type_name,
+            );
+        }
+    }
+}
+
+// Parses `text` with parse_domain_bounds, reporting a domain that couldn't
+// be parsed or whose min exceeds its max. Returns None if the domain should
+// be rejected.
+#[allow(clippy::type_complexity)]
+fn check_domain_bounds<T: core::str::FromStr + PartialOrd + Display>(
+    name: &str,
+    text: &str,
+) -> Option<(Option<T>, Option<T>)> {
+    let Some((min, max)) = parse_domain_bounds::<T>(text) else {
+        com::println!(
+            console::Channel::DONT_FILTER,
+            "dvar {}: couldn\'t parse domain \"{}\"",
+            name,
+            text,
+        );
+        return None;
+    };
+
+    if let (Some(min), Some(max)) = (&min, &max) {
+        if min > max {
+            com::println!(
+                console::Channel::DONT_FILTER,
+                "dvar {}: min {} should not be greater than max {}",
+                name,
+                min,
+                max,
+            );
+            return None;
+        }
+    }
+
+    Some((min, max))
+}
+
+// Parses the components of a vector from argv[2..2 + N], and its domain
+// from the remaining arguments. Prints the usage for `dvar_vecN` and
+// returns None if the arguments are malformed.
+#[allow(clippy::type_complexity)]
+fn parse_vector_args<const N: usize>(
+) -> Option<(String, [f32; N], Option<f32>, Option<f32>)> {
+    let argc = cmd::argc();
+    if argc < 2 + N {
+        const COMPONENTS: [&str; 4] = ["<x>", "<y>", "<z>", "<w>"];
+        com::println!(
+            console::Channel::DONT_FILTER,
+            "USAGE: {} <name> {} [<min> <max> | <domain>]",
+            cmd::argv(0),
+            COMPONENTS.get(..N).unwrap_or_default().join(" "),
+        );
+        return None;
+    }
+
+    let name = cmd::argv(1);
+    let mut value = [0.0; N];
+    for (i, v) in value.iter_mut().enumerate() {
+        *v = cmd::argv(2 + i).parse::<f32>().unwrap_or(0.0);
+    }
+
+    let domain = (2 + N..argc).map(cmd::argv).collect::<Vec<_>>().join(" ");
+    let (min, max) = check_domain_bounds::<f32>(&name, &domain)?;
+    Some((name, value, min, max))
+}
+
+fn register_vector2_f() {
+    let Some((name, [x, y], min, max)) = parse_vector_args::<2>() else {
+        return;
+    };
+
+    register_external(&name, "vec2", || {
+        register_vector2(
+            &name,
+            (x, y),
+            min,
+            max,
+            DvarFlags::EXTERNAL,
+            Some("External Dvar"),
+        )
+    });
+}
+
+fn register_vector3_f() {
+    let Some((name, [x, y, z], min, max)) = parse_vector_args::<3>() else {
+        return;
+    };
+
+    register_external(&name, "vec3", || {
+        register_vector3(
+            &name,
+            (x, y, z),
+            min,
+            max,
+            DvarFlags::EXTERNAL,
+            Some("External Dvar"),
+        )
+    });
+}
+
+fn register_vector4_f() {
+    let Some((name, [x, y, z, w], min, max)) = parse_vector_args::<4>() else {
+        return;
+    };
+
+    register_external(&name, "vec4", || {
+        register_vector4(
+            &name,
+            (x, y, z, w),
+            min,
+            max,
+            DvarFlags::EXTERNAL,
+            Some("External Dvar"),
+        )
+    });
+}
+
+fn register_int64_f() {
+    let argc = cmd::argc();
+    if argc < 3 {
+        com::println!(
+            console::Channel::DONT_FILTER,
+            "USAGE: {} <name> <default> [<min> <max> | <domain>]",
+            cmd::argv(0),
+        );
+        return;
+    }
+
+    let name = cmd::argv(1);
+    let value = cmd::argv(2).parse::<i64>().unwrap_or(0);
+    let domain = (3..argc).map(cmd::argv).collect::<Vec<_>>().join(" ");
+    let Some((min, max)) = check_domain_bounds::<i64>(&name, &domain) else {
+        return;
+    };
+
+    register_external(&name, "int64", || {
+        register_int64(
+            &name,
+            value,
+            min,
+            max,
+            DvarFlags::EXTERNAL,
+            Some("External Dvar"),
+        )
+    });
+}
+
+fn register_string_f() {
+    if cmd::argc() != 3 {
+        com::println!(
+            console::Channel::DONT_FILTER,
+            "USAGE: {} <name> <default>",
+            cmd::argv(0),
+        );
+        return;
+    }
+
+    let name = cmd::argv(1);
+    let value = cmd::argv(2);
+    register_external(&name, "string", || {
+        register_string(
+            &name,
+            &value,
+            DvarFlags::EXTERNAL,
+            Some("External Dvar"),
+        )
+    });
+}
+
+fn register_enumeration_f() {
+    let argc = cmd::argc();
+    if argc < 4 {
+        com::println!(
+            console::Channel::DONT_FILTER,
+            "USAGE: {} <name> <default> <value> [value...]",
+            cmd::argv(0),
+        );
+        return;
+    }
+
+    let name = cmd::argv(1);
+    // Resolve the default against the domain as it'll be stored (i.e.,
+    // without duplicates), so that indices mean the same thing as they do
+    // when setting the Dvar later
+    let strings = (3..argc).map(cmd::argv).collect::<Vec<_>>();
+    let domain = DvarLimitsEnumeration::new(&strings);
+    let default = cmd::argv(2);
+    let Some(value) = domain.resolve(&default).cloned() else {
+        com::println!(
+            console::Channel::DONT_FILTER,
+            "dvar {}: default \"{}\" is not in the domain",
+            name,
+            default,
+        );
+        return;
+    };
+
+    register_external(&name, "enum", || {
+        register_enumeration(
+            &name,
+            value,
+            Some(domain.strings),
+            DvarFlags::EXTERNAL,
+            Some("External Dvar"),
+        )
+    });
+}
+
 fn setu_f() {
     let argc = cmd::argc();
     if argc < 3 {
@@ -872,9 +1252,42 @@ fn setu_f() {
     add_flags(&name, DvarFlags::USER_INFO).unwrap();
 }
 
-#[allow(clippy::todo)]
+// Sets a batch of Dvars at once, either from name/value pairs or from a
+// single info string
 fn set_all_client_dvars_f() {
-    todo!()
+    let argc = cmd::argc();
+    let pairs = if argc == 2 {
+        info::parse(&cmd::argv(1))
+    } else if argc >= 3 && argc % 2 == 1 {
+        (1..argc)
+            .step_by(2)
+            .map(|i| (cmd::argv(i), cmd::argv(i + 1)))
+            .collect()
+    } else {
+        com::println!(
+            console::Channel::DONT_FILTER,
+            "USAGE: setAllClientDvars <variable> <value> [<variable> <value> \
+             ...]"
+        );
+        com::println!(
+            console::Channel::DONT_FILTER,
+            "       setAllClientDvars <info string>"
+        );
+        return;
+    };
+
+    for (name, value) in pairs {
+        if !name_is_valid(&name) {
+            com::println!(
+                console::Channel::DONT_FILTER,
+                "invalid variable name: {}",
+                name
+            );
+            continue;
+        }
+
+        set_command(&name, &value);
+    }
 }
 
 fn restore_dvars() {
@@ -888,36 +1301,94 @@ fn restore_dvars() {
             d.set_variant(d.saved.clone(), SetSource::Internal);
         }
     });
+    run_change_callbacks();
 }
 
-fn display_dvar(dvar: &Dvar, i: &mut i32) {
+fn display_dvar(dvar: &Dvar, i: &mut usize) {
     if dvar.flags.contains(DvarFlags::SAVED) {
         *i += 1;
         com::println!(
             console::Channel::DONT_FILTER,
-            " {} \"{}\"",
+            " {} \"{}^7\"{}",
             dvar.name,
-            dvar
+            dvar.saved,
+            if dvar.loaded_from_save_game {
+                " (loaded)"
+            } else {
+                ""
+            }
         );
     }
 }
 
-#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
 fn list_saved_dvars() {
+    let mut dvars = DVARS.read().unwrap().values().cloned().collect::<Vec<_>>();
+    dvars.sort_by(|a, b| a.name.cmp(&b.name));
+
     let mut i = 0;
-    DVARS
-        .read()
-        .unwrap()
-        .values()
-        .enumerate()
-        .for_each(|(j, d)| {
-            display_dvar(d, &mut (j as _));
-            i = j;
-        });
+    dvars.iter().for_each(|d| display_dvar(d, &mut i));
 
     com::println!(console::Channel::DONT_FILTER, "\n{} total SAVED dvars", i);
 }
 
+fn export_f() {
+    if cmd::argc() != 2 {
+        com::println!(
+            console::Channel::DONT_FILTER,
+            "USAGE: dvarexport <file>"
+        );
+        return;
+    }
+
+    let mut filename = PathBuf::from(cmd::argv(1));
+    if filename.extension().is_none() {
+        filename.set_extension("json");
+    }
+
+    let Ok(text) = export(ExportFormat::from_path(&filename)) else {
+        return;
+    };
+
+    if fs::write_file(&filename, text.as_bytes()).is_ok() {
+        com::println!(
+            console::Channel::DONT_FILTER,
+            "Exported dvars to {}",
+            filename.display()
+        );
+    }
+}
+
+fn import_f() {
+    if cmd::argc() != 2 {
+        com::println!(
+            console::Channel::DONT_FILTER,
+            "USAGE: dvarimport <file>"
+        );
+        return;
+    }
+
+    let filename = PathBuf::from(cmd::argv(1));
+    let Ok(file) = fs::read_file(&filename) else {
+        com::println!(
+            console::Channel::FILES,
+            "dvarimport: couldn\'t read {}",
+            filename.display()
+        );
+        return;
+    };
+
+    let text = String::from_utf8_lossy(&file).into_owned();
+    drop(file);
+    if let Ok(count) = import(&text, ExportFormat::from_path(&filename)) {
+        com::println!(
+            console::Channel::DONT_FILTER,
+            "Imported {} dvars from {}",
+            count,
+            filename.display()
+        );
+    }
+}
+
 /// Adds commands for Dvar module
 pub fn add_commands() {
     cmd::add_command_internal("toggle", toggle_f).unwrap();
@@ -933,10 +1404,18 @@ pub fn add_commands() {
     cmd::add_command_internal("reset", reset_f).unwrap();
     cmd::add_command_internal("dvarlist", list_f).unwrap();
     cmd::add_command_internal("dvardump", dump_f).unwrap();
+    cmd::add_command_internal("dvarexport", export_f).unwrap();
+    cmd::add_command_internal("dvarimport", import_f).unwrap();
     cmd::add_command_internal("dvar_bool", register_bool_f).unwrap();
     cmd::add_command_internal("dvar_int", register_int_f).unwrap();
     cmd::add_command_internal("dvar_float", register_float_f).unwrap();
     cmd::add_command_internal("dvar_color", register_color_f).unwrap();
+    cmd::add_command_internal("dvar_vec2", register_vector2_f).unwrap();
+    cmd::add_command_internal("dvar_vec3", register_vector3_f).unwrap();
+    cmd::add_command_internal("dvar_vec4", register_vector4_f).unwrap();
+    cmd::add_command_internal("dvar_int64", register_int64_f).unwrap();
+    cmd::add_command_internal("dvar_string", register_string_f).unwrap();
+    cmd::add_command_internal("dvar_enum", register_enumeration_f).unwrap();
     cmd::add_command_internal("setu", setu_f).unwrap();
     cmd::add_command_internal("setAllClientDvars", set_all_client_dvars_f)
         .unwrap();
@@ -0,0 +1,324 @@
+// This file implements exporting the Dvar registry to, and importing it
+// from, JSON and TOML. Every field of every Dvar is included, so that
+// external tools can diff configurations or generate documentation without
+// having to scrape the output of `dvarlist`.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+use super::{
+    callbacks::run_change_callbacks, find, handle, limits::DvarLimits,
+    name_is_valid, value::DvarValue, Dvar, DvarFlags, SetSource, DVARS,
+    DVAR_COUNT_MAX,
+};
+
+/// The formats the registry can be exported to and imported from.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ExportFormat {
+    Json,
+    Toml,
+}
+
+impl ExportFormat {
+    /// Picks a format based on the extension of `path`, defaulting to
+    /// [`ExportFormat::Json`] for anything that isn't `.toml`.
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => Self::Toml,
+            _ => Self::Json,
+        }
+    }
+}
+
+// bitflags 1.x has no way to convert between flags and their names, so we
+// keep our own table
+const FLAG_NAMES: [(&str, DvarFlags); 17] = [
+    ("ARCHIVE", DvarFlags::ARCHIVE),
+    ("USER_INFO", DvarFlags::USER_INFO),
+    ("SERVER_INFO", DvarFlags::SERVER_INFO),
+    ("SYSTEM_INFO", DvarFlags::SYSTEM_INFO),
+    ("WRITE_PROTECTED", DvarFlags::WRITE_PROTECTED),
+    ("LATCHED", DvarFlags::LATCHED),
+    ("READ_ONLY", DvarFlags::READ_ONLY),
+    ("CHEAT_PROTECTED", DvarFlags::CHEAT_PROTECTED),
+    ("UNKNOWN_00000100_D", DvarFlags::UNKNOWN_00000100_D),
+    ("CHANGEABLE_RESET", DvarFlags::CHANGEABLE_RESET),
+    ("UNKNOWN_00000400", DvarFlags::UNKNOWN_00000400),
+    ("ALLOW_SET_FROM_DEVGUI", DvarFlags::ALLOW_SET_FROM_DEVGUI),
+    ("SAVED", DvarFlags::SAVED),
+    ("UNKNOWN_00002000", DvarFlags::UNKNOWN_00002000),
+    ("EXTERNAL", DvarFlags::EXTERNAL),
+    ("AUTOEXEC", DvarFlags::AUTOEXEC),
+    ("CON_ACCESS", DvarFlags::CON_ACCESS),
+];
+
+fn flags_to_names(flags: DvarFlags) -> Vec<String> {
+    FLAG_NAMES
+        .iter()
+        .filter(|(_, f)| flags.contains(*f))
+        .map(|(n, _)| (*n).to_owned())
+        .collect()
+}
+
+fn names_to_flags(names: &[String]) -> Option<DvarFlags> {
+    names.iter().try_fold(DvarFlags::empty(), |flags, name| {
+        FLAG_NAMES
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, f)| flags | *f)
+    })
+}
+
+/// Serialized form of a single [`Dvar`].
+#[derive(Serialize, Deserialize)]
+struct DvarRecord {
+    name: String,
+    description: String,
+    /// Only written for the benefit of tools reading the export. The type
+    /// is taken from the values themselves on import.
+    #[serde(rename = "type", default)]
+    value_type: String,
+    flags: Vec<String>,
+    domain: DvarLimits,
+    current: DvarValue,
+    latched: DvarValue,
+    reset: DvarValue,
+    saved: DvarValue,
+}
+
+impl From<&Dvar> for DvarRecord {
+    fn from(dvar: &Dvar) -> Self {
+        Self {
+            name: dvar.name.clone(),
+            description: dvar.description.clone(),
+            value_type: dvar.current.type_name().to_owned(),
+            flags: flags_to_names(dvar.flags),
+            domain: dvar.domain.clone(),
+            current: dvar.current.clone(),
+            latched: dvar.latched.clone(),
+            reset: dvar.reset.clone(),
+            saved: dvar.saved.clone(),
+        }
+    }
+}
+
+/// Top-level serialized form of the registry. TOML requires the top level
+/// to be a table, so the records can't just be serialized as an array.
+#[derive(Serialize, Deserialize)]
+struct DvarRegistry {
+    dvars: Vec<DvarRecord>,
+}
+
+/// Serializes every registered [`Dvar`] into `format`.
+///
+/// Every field of each [`Dvar`] is included: its name, description, type,
+/// flags, domain, and current, latched, reset, and saved values. [`Dvar`]s
+/// are sorted by name so that exports can be diffed.
+///
+/// # Return Value
+///
+/// Returns the serialized registry on success, [`Err`] if serialization
+/// fails.
+///
+/// # Panics
+/// Panics if the read lock for [`DVARS`] can't be acquired (usually because
+/// the write lock is held by a function farther up the call stack).
+///
+/// # Example
+/// ```
+/// let json = export(ExportFormat::Json).unwrap();
+/// ```
+pub fn export(format: ExportFormat) -> Result<String, ()> {
+    let mut dvars = DVARS
+        .read()
+        .unwrap()
+        .values()
+        .map(|d| DvarRecord::from(&**d))
+        .collect::<Vec<_>>();
+    dvars.sort_by(|a, b| a.name.cmp(&b.name));
+    let registry = DvarRegistry { dvars };
+
+    match format {
+        ExportFormat::Json => {
+            serde_json::to_string_pretty(&registry).map_err(|e| {
+                com::println!(console::Channel::ERROR, "dvar::export: {}", e);
+            })
+        }
+        ExportFormat::Toml => toml::to_string_pretty(&registry).map_err(|e| {
+            com::println!(console::Channel::ERROR, "dvar::export: {}", e);
+        }),
+    }
+}
+
+/// Deserializes [`Dvar`]s from `text` (in `format`) into the registry.
+///
+/// If a [`Dvar`] in `text` is already registered, only its current value
+/// is imported, and it's set the same way the `set` command would set it,
+/// so read-only, write-protected and cheat-protected [`Dvar`]s keep their
+/// values. Otherwise, the [`Dvar`] is registered as it is in `text`. Change
+/// callbacks are run for every [`Dvar`] whose current value changes as a
+/// result.
+///
+/// Nothing is imported if `text` can't be parsed, if any record has an
+/// invalid or duplicate name, names an unknown flag, has an invalid domain
+/// (see [`DvarLimits::is_valid`]), or has values that don't all match the
+/// type of its domain (or of the registered [`Dvar`]) or fall outside of
+/// it. The same goes if a record that would be registered is protected, or
+/// if registering them all would exceed the maximum number of [`Dvar`]s.
+///
+/// # Return Value
+///
+/// Returns the number of [`Dvar`]s imported on success.
+///
+/// # Panics
+/// Panics if the write lock for [`DVARS`] can't be acquired (usually because
+/// the write lock or a read lock is held by a function farther up the
+/// call stack).
+///
+/// # Example
+/// ```
+/// let text = fs::read_file("dvars.json").unwrap();
+/// import(&String::from_utf8_lossy(&text), ExportFormat::Json).unwrap();
+/// ```
+pub fn import(text: &str, format: ExportFormat) -> Result<usize, ()> {
+    let registry = match format {
+        ExportFormat::Json => serde_json::from_str::<DvarRegistry>(text)
+            .map_err(|e| e.to_string()),
+        ExportFormat::Toml => {
+            toml::from_str::<DvarRegistry>(text).map_err(|e| e.to_string())
+        }
+    }
+    .map_err(|e| {
+        com::println!(console::Channel::ERROR, "dvar::import: {}", e);
+    })?;
+
+    let mut dvars = Vec::with_capacity(registry.dvars.len());
+    for record in registry.dvars {
+        let Some(flags) = names_to_flags(&record.flags) else {
+            com::println!(
+                console::Channel::ERROR,
+                "dvar::import: dvar \'{}\' has an unknown flag",
+                record.name
+            );
+            return Err(());
+        };
+
+        let dvar = Dvar {
+            name: record.name,
+            description: record.description,
+            flags,
+            modified: false,
+            loaded_from_save_game: false,
+            domain: record.domain,
+            current: record.current,
+            latched: record.latched,
+            reset: record.reset,
+            saved: record.saved,
+        };
+
+        if dvar.name.is_empty() || !name_is_valid(&dvar.name) {
+            com::println!(
+                console::Channel::ERROR,
+                "dvar::import: \'{}\' is not a valid dvar name",
+                dvar.name
+            );
+            return Err(());
+        }
+
+        if dvars.iter().any(|d: &Dvar| d.name == dvar.name) {
+            com::println!(
+                console::Channel::ERROR,
+                "dvar::import: dvar \'{}\' is listed more than once",
+                dvar.name
+            );
+            return Err(());
+        }
+
+        if !dvar.values_match_domain_type() {
+            com::println!(
+                console::Channel::ERROR,
+                "dvar::import: dvar \'{}\' has values of the wrong type",
+                dvar.name
+            );
+            return Err(());
+        }
+
+        if !dvar.domain.is_valid()
+            || [&dvar.current, &dvar.latched, &dvar.reset]
+                .into_iter()
+                .any(|v| !Dvar::value_is_in_domain(&dvar.domain, v.clone()))
+        {
+            com::println!(
+                console::Channel::ERROR,
+                "dvar::import: dvar \'{}\' has values outside of its domain",
+                dvar.name
+            );
+            return Err(());
+        }
+
+        dvars.push(dvar);
+    }
+
+    // Registered Dvars only take the value, so check it against them
+    // before anything is imported
+    let mut new_count = 0;
+    for dvar in &dvars {
+        if let Some(old) = find(&dvar.name) {
+            if old.current.type_name() != dvar.current.type_name() {
+                com::println!(
+                    console::Channel::ERROR,
+                    "dvar::import: dvar \'{}\' is registered as {}, not {}",
+                    dvar.name,
+                    old.current.type_name(),
+                    dvar.current.type_name()
+                );
+                return Err(());
+            }
+            continue;
+        }
+
+        if dvar.flags.intersects(
+            DvarFlags::READ_ONLY
+                | DvarFlags::WRITE_PROTECTED
+                | DvarFlags::CHEAT_PROTECTED,
+        ) {
+            com::println!(
+                console::Channel::ERROR,
+                "dvar::import: can\'t create protected dvar \'{}\'",
+                dvar.name
+            );
+            return Err(());
+        }
+        new_count += 1;
+    }
+
+    if DVARS.read().unwrap().len() + new_count > DVAR_COUNT_MAX {
+        com::println!(
+            console::Channel::ERROR,
+            "dvar::import: can\'t create {} dvars: {} dvars already exist",
+            new_count,
+            DVARS.read().unwrap().len()
+        );
+        return Err(());
+    }
+
+    let count = dvars.len();
+    {
+        let mut writer = DVARS.write().unwrap();
+        for dvar in dvars {
+            match writer.get_mut(&dvar.name) {
+                Some(old) => old.set_variant(dvar.current, SetSource::External),
+                None => {
+                    handle::sync(&dvar.name, &dvar.current);
+                    writer.insert(dvar.name.clone(), Box::new(dvar));
+                }
+            }
+        }
+    }
+    run_change_callbacks();
+
+    Ok(count)
+}
@@ -4,9 +4,9 @@ use std::{collections::HashMap, sync::RwLock};
 
 use lazy_static::lazy_static;
 
-use crate::dvar::Dvar;
+use crate::{com, console, dvar::Dvar, info};
 
-use super::DvarFlags;
+use super::{callbacks::run_change_callbacks, DvarFlags, SetSource};
 
 pub mod register;
 pub use register::*;
@@ -17,13 +17,62 @@ pub use set::*;
 pub mod get;
 pub use get::*;
 
-const DVAR_COUNT_MAX: usize = 4096;
+pub(super) const DVAR_COUNT_MAX: usize = 4096;
 
 lazy_static! {
     pub(super) static ref DVARS: RwLock<HashMap<String, Box<Dvar>>> =
         RwLock::new(HashMap::new());
 }
 
+/// Inserts a newly-registered [`Dvar`] into [`DVARS`].
+///
+/// Setting a [`Dvar`] that doesn't exist yet (e.g. with `+set` on the
+/// command line, or from a config file) creates an external string
+/// [`Dvar`] to hold the value until the real one is registered. Likewise,
+/// the `dvar_*` commands create external [`Dvar`]s of other types. If
+/// `dvar` replaces an external [`Dvar`], the external one's value is
+/// converted to `dvar`'s type and applied to it, and any flags added to the
+/// external one (e.g. [`DvarFlags::ARCHIVE`] from `seta`) are added to
+/// `dvar`.
+///
+/// # Return Value
+///
+/// Returns the replaced [`Dvar`] if a registered [`Dvar`] with the same
+/// name already existed (i.e. there's a name collision), [`None`]
+/// otherwise.
+///
+/// # Panics
+/// Panics if the write lock for [`DVARS`] can't be acquired (usually because
+/// the write lock or a read lock is held by a function farther up the
+/// call stack).
+pub(super) fn insert_registered(dvar: Dvar) -> Option<Box<Dvar>> {
+    let name = dvar.name.clone();
+    let external_value = {
+        let mut dvars = DVARS.write().unwrap();
+        let old = dvars.insert(name.clone(), Box::new(dvar))?;
+        if !old.flags.contains(DvarFlags::EXTERNAL) {
+            return Some(old);
+        }
+
+        dvars
+            .get_mut(&name)
+            .unwrap()
+            .add_flags(old.flags & !DvarFlags::EXTERNAL);
+        // Goes through the same parsing as `set`, which accepts the format
+        // values are displayed in
+        old.current.to_string()
+    };
+
+    // Internal, since the value was set before any restrictions (e.g.
+    // write protection) on the registered Dvar existed
+    let _ = set_from_string_from_source(
+        &name,
+        &external_value,
+        SetSource::Internal,
+    );
+    None
+}
+
 /// Finds a previously-registered [`Dvar`] by name and returns a copy if
 /// present.
 ///
@@ -94,6 +143,22 @@ pub fn exists(name: &str) -> bool {
     find(name).is_some()
 }
 
+/// Returns the names of every registered [`Dvar`], sorted.
+///
+/// # Panics
+/// Panics if the read lock for [`DVARS`] can't be acquired (usually because
+/// the write lock is held by a function farther up the call stack).
+///
+/// Example
+/// ```
+/// let count = names().iter().filter(|n| n.starts_with("fs_")).count();
+/// ```
+pub fn names() -> Vec<String> {
+    let mut names = DVARS.read().unwrap().keys().cloned().collect::<Vec<_>>();
+    names.sort_unstable();
+    names
+}
+
 /// Clears the `modified` flag of a [`Dvar`], if it exists.
 ///
 /// # Arguments
@@ -195,12 +260,172 @@ pub fn make_latched_value_current(name: &str) -> Result<(), ()> {
     let mut writer = DVARS.write().unwrap();
     if let Some(d) = writer.get_mut(name) {
         d.make_latched_value_current();
+        drop(writer);
+        run_change_callbacks();
         return Ok(());
     };
 
     Err(())
 }
 
+/// Builds the config file lines needed to restore every archived [`Dvar`].
+///
+/// A `seta name "value"` line is produced for each [`Dvar`] with
+/// [`DvarFlags::ARCHIVE`] set whose value differs from its reset value, and
+/// for each external [`Dvar`] created by `seta` (whose value is, by
+/// definition, its reset value). For [`DvarFlags::LATCHED`] [`Dvar`]s, the
+/// latched value is written, so that a change waiting on a restart isn't
+/// lost. Lines are sorted by name so that config files diff cleanly.
+///
+/// # Panics
+/// Panics if the read lock for [`DVARS`] can't be acquired (usually because
+/// the write lock is held by a function farther up the call stack).
+///
+/// Example
+/// ```
+/// let text = write_variables();
+/// fs::write_file("config_mp.cfg", text.as_bytes());
+/// ```
+pub fn write_variables() -> String {
+    let dvars = DVARS.read().unwrap();
+    let mut archived = dvars
+        .values()
+        .filter(|d| d.flags.contains(DvarFlags::ARCHIVE))
+        .filter_map(|d| {
+            let value = if d.flags.contains(DvarFlags::LATCHED) {
+                &d.latched
+            } else {
+                &d.current
+            };
+            (*value != d.reset || d.flags.contains(DvarFlags::EXTERNAL))
+                .then_some((&d.name, value))
+        })
+        .collect::<Vec<_>>();
+    archived.sort_by(|a, b| a.0.cmp(b.0));
+
+    archived
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "seta {} \"{}\"\n",
+                name,
+                value.to_string().replace('"', "\\\"")
+            )
+        })
+        .collect()
+}
+
+/// Prints everything known about every [`Dvar`] whose name matches
+/// `pattern` to `channel`.
+///
+/// For each [`Dvar`], its name, type, current, latched, and reset values,
+/// domain, flags, and description are printed. [`Dvar`]s are printed in
+/// order of name. An empty `pattern` matches every [`Dvar`]; otherwise,
+/// `pattern` is matched with [`com::filter`].
+///
+/// # Return Value
+///
+/// Returns the number of [`Dvar`]s printed.
+///
+/// # Panics
+/// Panics if the read lock for [`DVARS`] can't be acquired (usually because
+/// the write lock is held by a function farther up the call stack).
+///
+/// Example
+/// ```
+/// dump(console::Channel::DONT_FILTER, "r_*");
+/// ```
+pub fn dump(channel: console::Channel, pattern: &str) -> usize {
+    let dvars = DVARS.read().unwrap();
+    let mut matching = dvars
+        .values()
+        .filter(|d| pattern.is_empty() || com::filter(pattern, &d.name, false))
+        .collect::<Vec<_>>();
+    matching.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for d in &matching {
+        let flags = if d.flags.is_empty() {
+            "none".to_owned()
+        } else {
+            format!("{:?}", d.flags)
+        };
+
+        com::println!(channel, "{} ({})", d.name, d.current.type_name());
+        com::println!(
+            channel,
+            "    current: \"{}\" latched: \"{}\" reset: \"{}\"",
+            d.current,
+            d.latched,
+            d.reset,
+        );
+        com::println!(channel, "    {}", d.domain);
+        com::println!(channel, "    flags: {}", flags);
+        if !d.description.is_empty() {
+            com::println!(channel, "    {}", d.description);
+        }
+    }
+
+    matching.len()
+}
+
+fn info_string_with_limit(flags: DvarFlags, big: bool) -> String {
+    let dvars = DVARS.read().unwrap();
+    let mut matching = dvars
+        .values()
+        .filter(|d| d.flags.intersects(flags))
+        .collect::<Vec<_>>();
+    matching.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut info = String::new();
+    for d in matching {
+        let value = d.current.to_string();
+        // Errors are already reported by info::set_value_for_key, and the
+        // Dvar is just left out of the string
+        let _ = if big {
+            info::set_value_for_key_big(&mut info, &d.name, &value)
+        } else {
+            info::set_value_for_key(&mut info, &d.name, &value)
+        };
+    }
+
+    info
+}
+
+/// Builds an info string (`\key\value\key\value...`) from every [`Dvar`]
+/// with any of `flags` set (usually [`DvarFlags::USER_INFO`] or
+/// [`DvarFlags::SERVER_INFO`]).
+///
+/// The string is limited to [`info::MAX_INFO_STRING`] characters. [`Dvar`]s
+/// that don't fit, or whose values can't be embedded in an info string,
+/// are left out.
+///
+/// # Panics
+/// Panics if the read lock for [`DVARS`] can't be acquired (usually because
+/// the write lock is held by a function farther up the call stack).
+///
+/// Example
+/// ```
+/// let userinfo = info_string(DvarFlags::USER_INFO);
+/// ```
+pub fn info_string(flags: DvarFlags) -> String {
+    info_string_with_limit(flags, false)
+}
+
+/// Like [`info_string`], but limited to [`info::BIG_INFO_STRING`]
+/// characters. Used for [`DvarFlags::SYSTEM_INFO`].
+///
+/// # Panics
+/// Panics if the read lock for [`DVARS`] can't be acquired (usually because
+/// the write lock is held by a function farther up the call stack).
+///
+/// Example
+/// ```
+/// let systeminfo = info_string_big(DvarFlags::SYSTEM_INFO);
+/// ```
+pub fn info_string_big(flags: DvarFlags) -> String {
+    info_string_with_limit(flags, true)
+}
+
 // Helper function to check if Dvar name is valid
 // Valid names consist only of alphanumeric characters and underscores
 pub fn name_is_valid(name: &str) -> bool {
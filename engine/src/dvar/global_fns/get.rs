@@ -964,3 +964,27 @@ pub fn get_or_register_color_xyz(
         Some((x, y, z))
     })
 }
+
+/// Retrieves the flags of a [`Dvar`].
+///
+/// # Arguments
+/// * `name` - A [`String`] that holds the name of the [`Dvar`]
+/// to be retrieved.
+///
+/// # Return Value
+///
+/// Returns [`Some`] if a [`Dvar`] with name `name` exists, [`None`]
+/// otherwise.
+///
+/// # Panics
+/// Panics if the write lock for [`DVARS`] can't be acquired (usually because
+/// the write lock or a read lock is held by a function farther up the
+/// call stack).
+///
+/// Example
+/// ```
+/// let archived = get_flags("sv_test").unwrap().contains(DvarFlags::ARCHIVE);
+/// ```
+pub fn get_flags(name: &str) -> Option<DvarFlags> {
+    find(name).map(|d| d.flags)
+}
@@ -1,10 +1,14 @@
 use crate::{
     com,
     common::{Vec2f32, Vec3f32, Vec4f32},
-    dvar::{builder::DvarBuilder, DvarFlags},
+    dvar::{
+        builder::DvarBuilder,
+        handle::{self, DvarHandle},
+        DvarFlags,
+    },
 };
 
-use super::{exists, DVARS, DVAR_COUNT_MAX};
+use super::{exists, insert_registered, DVARS, DVAR_COUNT_MAX};
 
 /// Registers a new [`Dvar`] of type [`DvarValue::Bool`],
 /// using the provided name, value, flags, and description,
@@ -22,7 +26,8 @@ use super::{exists, DVARS, DVAR_COUNT_MAX};
 ///
 /// # Return Value
 ///
-/// Returns true if (re)registration was successful, false otherwise.
+/// Returns a [`DvarHandle`] to the [`Dvar`] if (re)registration was
+/// successful, [`Err`] otherwise.
 ///
 /// # Example
 /// ```
@@ -37,7 +42,7 @@ pub fn register_bool(
     value: bool,
     flags: DvarFlags,
     description: Option<&str>,
-) -> Result<(), ()> {
+) -> Result<DvarHandle<bool>, ()> {
     if DVARS.read().unwrap().len() + 1 > DVAR_COUNT_MAX {
         com::errorln!(
             com::ErrorParm::FATAL,
@@ -55,12 +60,7 @@ pub fn register_bool(
         .type_bool()
         .value(value)
         .build();
-    if DVARS
-        .write()
-        .unwrap()
-        .insert(name.to_owned(), Box::new(dvar))
-        .is_some()
-    {
+    if insert_registered(dvar).is_some() {
         let other_name = DVARS.read().unwrap().get(name).unwrap().name.clone();
         com::errorln!(
             com::ErrorParm::FATAL,
@@ -71,7 +71,7 @@ pub fn register_bool(
         );
         Err(())
     } else {
-        Ok(())
+        Ok(handle::for_registered(name))
     }
 }
 
@@ -89,8 +89,8 @@ pub fn register_bool(
 ///
 /// # Return Value
 ///
-/// Returns true if [`Dvar`] doesn't exist and registration
-/// was successful, false otherwise.
+/// Returns a [`DvarHandle`] to the [`Dvar`] if it didn't exist and
+/// registration was successful, [`Err`] otherwise.
 ///
 /// # Example
 /// ```
@@ -107,7 +107,7 @@ pub fn register_new_bool(
     value: bool,
     flags: DvarFlags,
     description: Option<&str>,
-) -> Result<(), ()> {
+) -> Result<DvarHandle<bool>, ()> {
     if exists(name) {
         return Err(());
     }
@@ -128,8 +128,8 @@ pub fn register_new_bool(
 ///
 /// # Return Value
 ///
-/// Returns true if [`Dvar`] exists and reregistration
-/// was successful, false otherwise.
+/// Returns a [`DvarHandle`] to the [`Dvar`] if it existed and
+/// reregistration was successful, [`Err`] otherwise.
 ///
 /// # Example
 /// ```
@@ -146,7 +146,7 @@ pub fn reregister_bool(
     value: bool,
     flags: DvarFlags,
     description: Option<&str>,
-) -> Result<(), ()> {
+) -> Result<DvarHandle<bool>, ()> {
     if !exists(name) {
         return Err(());
     }
@@ -173,7 +173,8 @@ pub fn reregister_bool(
 ///
 /// # Return Value
 ///
-/// Returns true if registration was successful, false otherwise.
+/// Returns a [`DvarHandle`] to the [`Dvar`] if (re)registration was
+/// successful, [`Err`] otherwise.
 ///
 /// # Example
 /// ```
@@ -193,7 +194,7 @@ pub fn register_float(
     max: Option<f32>,
     flags: DvarFlags,
     description: Option<&str>,
-) -> Result<(), ()> {
+) -> Result<DvarHandle<f32>, ()> {
     if DVARS.read().unwrap().len() + 1 > DVAR_COUNT_MAX {
         com::errorln!(
             com::ErrorParm::FATAL,
@@ -212,12 +213,7 @@ pub fn register_float(
         .domain(min.unwrap_or(f32::MIN), max.unwrap_or(f32::MAX))
         .value(value)
         .build();
-    if DVARS
-        .write()
-        .unwrap()
-        .insert(name.to_owned(), Box::new(dvar))
-        .is_some()
-    {
+    if insert_registered(dvar).is_some() {
         let other_name = DVARS.read().unwrap().get(name).unwrap().name.clone();
         com::errorln!(
             com::ErrorParm::FATAL,
@@ -228,7 +224,7 @@ pub fn register_float(
         );
         Err(())
     } else {
-        Ok(())
+        Ok(handle::for_registered(name))
     }
 }
 
@@ -250,8 +246,8 @@ pub fn register_float(
 ///
 /// # Return Value
 ///
-/// Returns true if [`Dvar`] doesn't exist and registration
-/// was successful, false otherwise.
+/// Returns a [`DvarHandle`] to the [`Dvar`] if it didn't exist and
+/// registration was successful, [`Err`] otherwise.
 ///
 /// # Example
 /// ```
@@ -273,7 +269,7 @@ pub fn register_new_float(
     max: Option<f32>,
     flags: DvarFlags,
     description: Option<&str>,
-) -> Result<(), ()> {
+) -> Result<DvarHandle<f32>, ()> {
     if exists(name) {
         return Err(());
     }
@@ -299,8 +295,8 @@ pub fn register_new_float(
 ///
 /// # Return Value
 ///
-/// Returns true if [`Dvar`] exists and reregistration
-/// was successful, false otherwise.
+/// Returns a [`DvarHandle`] to the [`Dvar`] if it existed and
+/// reregistration was successful, [`Err`] otherwise.
 ///
 /// # Example
 /// ```
@@ -322,7 +318,7 @@ pub fn reregister_float(
     max: Option<f32>,
     flags: DvarFlags,
     description: Option<&str>,
-) -> Result<(), ()> {
+) -> Result<DvarHandle<f32>, ()> {
     if !exists(name) {
         return Err(());
     }
@@ -390,12 +386,7 @@ pub fn register_vector2(
         .value(value)
         .build();
 
-    if DVARS
-        .write()
-        .unwrap()
-        .insert(name.to_owned(), Box::new(dvar))
-        .is_some()
-    {
+    if insert_registered(dvar).is_some() {
         let other_name = DVARS.read().unwrap().get(name).unwrap().name.clone();
         com::errorln!(
             com::ErrorParm::FATAL,
@@ -568,12 +559,7 @@ pub fn register_vector3(
         .domain(min.unwrap_or(f32::MIN), max.unwrap_or(f32::MAX))
         .value(value)
         .build();
-    if DVARS
-        .write()
-        .unwrap()
-        .insert(name.to_owned(), Box::new(dvar))
-        .is_some()
-    {
+    if insert_registered(dvar).is_some() {
         let other_name = DVARS.read().unwrap().get(name).unwrap().name.clone();
         com::errorln!(
             com::ErrorParm::FATAL,
@@ -747,12 +733,7 @@ pub fn register_vector4(
         .value(value)
         .build();
 
-    if DVARS
-        .write()
-        .unwrap()
-        .insert(name.to_owned(), Box::new(dvar))
-        .is_some()
-    {
+    if insert_registered(dvar).is_some() {
         let other_name = DVARS.read().unwrap().get(name).unwrap().name.clone();
         com::errorln!(
             com::ErrorParm::FATAL,
@@ -886,7 +867,8 @@ pub fn reregister_vector4(
 ///
 /// # Return Value
 ///
-/// Returns true if registration was successful, false otherwise.
+/// Returns a [`DvarHandle`] to the [`Dvar`] if (re)registration was
+/// successful, [`Err`] otherwise.
 ///
 /// # Example
 /// ```
@@ -906,7 +888,7 @@ pub fn register_int(
     max: Option<i32>,
     flags: DvarFlags,
     description: Option<&str>,
-) -> Result<(), ()> {
+) -> Result<DvarHandle<i32>, ()> {
     if DVARS.read().unwrap().len() + 1 > DVAR_COUNT_MAX {
         com::errorln!(
             com::ErrorParm::FATAL,
@@ -926,12 +908,7 @@ pub fn register_int(
         .value(value)
         .build();
 
-    if DVARS
-        .write()
-        .unwrap()
-        .insert(name.to_owned(), Box::new(dvar))
-        .is_some()
-    {
+    if insert_registered(dvar).is_some() {
         let other_name = DVARS.read().unwrap().get(name).unwrap().name.clone();
         com::errorln!(
             com::ErrorParm::FATAL,
@@ -942,7 +919,7 @@ pub fn register_int(
         );
         Err(())
     } else {
-        Ok(())
+        Ok(handle::for_registered(name))
     }
 }
 
@@ -963,8 +940,8 @@ pub fn register_int(
 ///
 /// # Return Value
 ///
-/// Returns true if [`Dvar`] doesn't exist and registration
-/// was successful, false otherwise.
+/// Returns a [`DvarHandle`] to the [`Dvar`] if it didn't exist and
+/// registration was successful, [`Err`] otherwise.
 ///
 /// # Example
 /// ```
@@ -986,7 +963,7 @@ pub fn register_new_int(
     max: Option<i32>,
     flags: DvarFlags,
     description: Option<&str>,
-) -> Result<(), ()> {
+) -> Result<DvarHandle<i32>, ()> {
     if exists(name) {
         return Err(());
     }
@@ -1013,8 +990,8 @@ pub fn register_new_int(
 ///
 /// # Return Value
 ///
-/// Returns true if [`Dvar`] exists and reregistration
-/// was successful, false otherwise.
+/// Returns a [`DvarHandle`] to the [`Dvar`] if it existed and
+/// reregistration was successful, [`Err`] otherwise.
 ///
 /// # Example
 /// ```
@@ -1036,7 +1013,7 @@ pub fn reregister_int(
     max: Option<i32>,
     flags: DvarFlags,
     description: Option<&str>,
-) -> Result<(), ()> {
+) -> Result<DvarHandle<i32>, ()> {
     if !exists(name) {
         return Err(());
     }
@@ -1094,12 +1071,7 @@ pub fn register_string(
         .value(value.to_owned())
         .build();
 
-    if DVARS
-        .write()
-        .unwrap()
-        .insert(name.to_owned(), Box::new(dvar))
-        .is_some()
-    {
+    if insert_registered(dvar).is_some() {
         let other_name = DVARS.read().unwrap().get(name).unwrap().name.clone();
         com::errorln!(
             com::ErrorParm::FATAL,
@@ -1248,12 +1220,7 @@ pub fn register_enumeration(
         .value(value)
         .build();
 
-    if DVARS
-        .write()
-        .unwrap()
-        .insert(name.to_owned(), Box::new(dvar))
-        .is_some()
-    {
+    if insert_registered(dvar).is_some() {
         let other_name = DVARS.read().unwrap().get(name).unwrap().name.clone();
         com::errorln!(
             com::ErrorParm::FATAL,
@@ -1424,12 +1391,7 @@ pub fn register_color(
         .value((r, g, b, a))
         .build();
 
-    if DVARS
-        .write()
-        .unwrap()
-        .insert(name.to_owned(), Box::new(dvar))
-        .is_some()
-    {
+    if insert_registered(dvar).is_some() {
         let other_name = DVARS.write().unwrap().get(name).unwrap().name.clone();
         com::errorln!(
             com::ErrorParm::FATAL,
@@ -1566,7 +1528,8 @@ pub fn reregister_color(
 ///
 /// # Return Value
 ///
-/// Returns true if registration was successful, false otherwise.
+/// Returns a [`DvarHandle`] to the [`Dvar`] if (re)registration was
+/// successful, [`Err`] otherwise.
 ///
 /// # Example
 /// ```
@@ -1585,7 +1548,7 @@ pub fn register_int64(
     max: Option<i64>,
     flags: DvarFlags,
     description: Option<&str>,
-) -> Result<(), ()> {
+) -> Result<DvarHandle<i64>, ()> {
     if DVARS.write().unwrap().len() + 1 > DVAR_COUNT_MAX {
         com::errorln!(
             com::ErrorParm::FATAL,
@@ -1605,12 +1568,7 @@ pub fn register_int64(
         .value(value)
         .build();
 
-    if DVARS
-        .write()
-        .unwrap()
-        .insert(name.to_owned(), Box::new(dvar))
-        .is_some()
-    {
+    if insert_registered(dvar).is_some() {
         let other_name = DVARS.write().unwrap().get(name).unwrap().name.clone();
         com::errorln!(
             com::ErrorParm::FATAL,
@@ -1621,7 +1579,7 @@ pub fn register_int64(
         );
         Err(())
     } else {
-        Ok(())
+        Ok(handle::for_registered(name))
     }
 }
 
@@ -1642,8 +1600,8 @@ pub fn register_int64(
 ///
 /// # Return Value
 ///
-/// Returns true if [`Dvar`] doesn't exist and registration
-/// was successful, false otherwise.
+/// Returns a [`DvarHandle`] to the [`Dvar`] if it didn't exist and
+/// registration was successful, [`Err`] otherwise.
 ///
 /// # Example
 /// ```
@@ -1664,7 +1622,7 @@ pub fn register_new_int64(
     max: Option<i64>,
     flags: DvarFlags,
     description: Option<&str>,
-) -> Result<(), ()> {
+) -> Result<DvarHandle<i64>, ()> {
     if exists(name) {
         return Err(());
     }
@@ -1691,8 +1649,8 @@ pub fn register_new_int64(
 ///
 /// # Return Value
 ///
-/// Returns true if [`Dvar`] exists and reregistration
-/// was successful, false otherwise.
+/// Returns a [`DvarHandle`] to the [`Dvar`] if it existed and
+/// reregistration was successful, [`Err`] otherwise.
 ///
 /// # Example
 /// ```
@@ -1713,7 +1671,7 @@ pub fn reregister_int64(
     max: Option<i64>,
     flags: DvarFlags,
     description: Option<&str>,
-) -> Result<(), ()> {
+) -> Result<DvarHandle<i64>, ()> {
     if !exists(name) {
         return Err(());
     }
@@ -1790,12 +1748,7 @@ pub fn register_linear_color_rgb(
         .value((red, green, blue))
         .build();
 
-    if DVARS
-        .write()
-        .unwrap()
-        .insert(name.to_owned(), Box::new(dvar))
-        .is_some()
-    {
+    if insert_registered(dvar).is_some() {
         let other_name = DVARS.write().unwrap().get(name).unwrap().name.clone();
         com::errorln!(
             com::ErrorParm::FATAL,
@@ -2008,12 +1961,7 @@ pub fn register_color_xyz(
         .domain(min.unwrap_or(f32::MIN), max.unwrap_or(f32::MAX))
         .value((x, y, z))
         .build();
-    if DVARS
-        .write()
-        .unwrap()
-        .insert(name.to_owned(), Box::new(dvar))
-        .is_some()
-    {
+    if insert_registered(dvar).is_some() {
         let other_name = DVARS.write().unwrap().get(name).unwrap().name.clone();
         com::errorln!(
             com::ErrorParm::FATAL,
@@ -1,6 +1,11 @@
 use crate::{
+    com,
     common::{Vec2f32, Vec3f32, Vec4f32},
-    dvar::{limits::DvarLimits, value::DvarValue, DvarFlags, SetSource},
+    console,
+    dvar::{
+        callbacks::run_change_callbacks, limits::DvarLimits, value::DvarValue,
+        DvarFlags, SetSource,
+    },
 };
 
 use super::{
@@ -56,6 +61,7 @@ pub fn set_variant_from_source(
                 .get_mut(name)
                 .unwrap()
                 .set_variant(value, source);
+            run_change_callbacks();
             Ok(())
         }
         None => Err(()),
@@ -212,7 +218,7 @@ pub fn set_or_register_bool(
     if exists(name) {
         set_bool_internal(name, value)
     } else {
-        register_bool(name, value, flags, description)
+        register_bool(name, value, flags, description).map(|_| ())
     }
 }
 
@@ -377,7 +383,7 @@ pub fn set_or_register_float(
     if exists(name) {
         set_float_internal(name, value)
     } else {
-        register_float(name, value, min, max, flags, description)
+        register_float(name, value, min, max, flags, description).map(|_| ())
     }
 }
 
@@ -1030,7 +1036,7 @@ pub fn set_or_register_int(
     if exists(name) {
         set_int_internal(name, value)
     } else {
-        register_int(name, value, min, max, flags, description)
+        register_int(name, value, min, max, flags, description).map(|_| ())
     }
 }
 
@@ -1074,6 +1080,58 @@ pub fn set_string_from_source(
     set_variant_from_source(name, DvarValue::String(value.to_owned()), source)
 }
 
+/// Sets the value of an existing [`Dvar`] from a string, converting it to
+/// the [`Dvar`]'s type first.
+///
+/// Unlike [`set_string_from_source`], which always stores a
+/// [`DvarValue::String`], this parses `value` into the type of the
+/// [`Dvar`]'s current value (e.g., `"1"` becomes [`DvarValue::Bool`] for a
+/// boolean [`Dvar`]). This is what should be used for values typed into the
+/// console or read from config files.
+///
+/// # Arguments
+/// * `name` - A [`String`] that holds the name of the [`Dvar`]
+/// to be updated.
+/// * `value` - The string to parse the [`Dvar`]'s new value from.
+/// * `source` - The [`SetSource`] to set the value with.
+///
+/// # Return Value
+///
+/// Returns [`Ok`] if the [`Dvar`] exists and `value` could be parsed,
+/// [`Err`] otherwise.
+///
+/// # Panics
+/// Panics if the write lock for [`DVARS`] can't be acquired (usually because
+/// the write lock or a read lock is held by a function farther up the
+/// call stack).
+///
+/// # Example
+/// ```
+/// set_from_string_from_source("sv_cheats", "1", SetSource::External);
+/// ```
+pub fn set_from_string_from_source(
+    name: &str,
+    value: &str,
+    source: SetSource,
+) -> Result<(), ()> {
+    let Some(dvar) = find(name) else {
+        return Err(());
+    };
+
+    let Some(value) = dvar.value_from_string(value) else {
+        com::println!(
+            console::Channel::ERROR,
+            "\'{}\' is not a valid value for dvar \'{}\'",
+            value,
+            name,
+        );
+        com::println!(console::Channel::ERROR, "{}", dvar.domain);
+        return Err(());
+    };
+
+    set_variant_from_source(name, value, source)
+}
+
 /// Sets the value of an existing [`Dvar`].
 ///
 /// Uses the supplied parameters to update an existing [`Dvar`] with name
@@ -1445,7 +1503,9 @@ pub fn add_to_enumeration_domain(
                 match &mut DVARS.write().unwrap().get_mut(name).unwrap().domain
                 {
                     DvarLimits::Enumeration(l) => {
-                        l.strings.insert(domain_str.to_owned());
+                        if !l.strings.iter().any(|s| s == domain_str) {
+                            l.strings.push(domain_str.to_owned());
+                        }
                         Ok(())
                     }
                     _ => Err(()),
@@ -1467,7 +1527,7 @@ pub fn remove_from_enumeration_domain(
                 match &mut DVARS.write().unwrap().get_mut(name).unwrap().domain
                 {
                     DvarLimits::Enumeration(l) => {
-                        l.strings.remove(&domain_str.to_owned());
+                        l.strings.retain(|s| s != domain_str);
                         Ok(())
                     }
                     _ => Err(()),
@@ -1870,7 +1930,7 @@ pub fn set_or_register_int64(
     if exists(name) {
         set_int64_internal(name, value)
     } else {
-        register_int64(name, value, min, max, flags, description)
+        register_int64(name, value, min, max, flags, description).map(|_| ())
     }
 }
 
@@ -0,0 +1,211 @@
+// This file implements typed handles to Dvars, for code that reads a Dvar
+// often enough (e.g. every frame) that taking the lock for DVARS and hashing
+// the name each time would be wasteful.
+//
+// Each Dvar that has been handed out a handle gets a cell holding its
+// current value as raw bits in an AtomicU64. `Dvar::set_variant` (and
+// anything else that changes a Dvar's current value) keeps the cell up to
+// date with `sync`, so reading through a handle is a single atomic load.
+
+use core::marker::PhantomData;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use lazy_static::lazy_static;
+
+use super::{value::DvarValue, DVARS};
+
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for bool {}
+    impl Sealed for i32 {}
+    impl Sealed for i64 {}
+    impl Sealed for f32 {}
+}
+
+/// Types that a [`DvarHandle`] can read.
+///
+/// Only types that fit in an [`AtomicU64`] are supported, so that reads
+/// never need to take a lock. This trait is sealed.
+pub trait HandleType: sealed::Sealed + Copy {
+    /// Extracts a value of this type from `value`, or returns [`None`] if
+    /// `value` is a different type.
+    fn from_value(value: &DvarValue) -> Option<Self>;
+    /// Converts the value into the raw bits stored in the cell.
+    fn to_bits(self) -> u64;
+    /// Converts raw bits stored in the cell back into a value.
+    fn from_bits(bits: u64) -> Self;
+}
+
+impl HandleType for bool {
+    fn from_value(value: &DvarValue) -> Option<Self> {
+        value.as_bool()
+    }
+
+    fn to_bits(self) -> u64 {
+        u64::from(self)
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        bits != 0
+    }
+}
+
+#[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+impl HandleType for i32 {
+    fn from_value(value: &DvarValue) -> Option<Self> {
+        value.as_int()
+    }
+
+    fn to_bits(self) -> u64 {
+        u64::from(self as u32)
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        bits as u32 as Self
+    }
+}
+
+#[allow(clippy::cast_sign_loss, clippy::cast_possible_wrap)]
+impl HandleType for i64 {
+    fn from_value(value: &DvarValue) -> Option<Self> {
+        value.as_int64()
+    }
+
+    fn to_bits(self) -> u64 {
+        self as u64
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        bits as Self
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+impl HandleType for f32 {
+    fn from_value(value: &DvarValue) -> Option<Self> {
+        value.as_float()
+    }
+
+    fn to_bits(self) -> u64 {
+        u64::from(self.to_bits())
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        Self::from_bits(bits as u32)
+    }
+}
+
+lazy_static! {
+    static ref CELLS: RwLock<HashMap<String, Arc<AtomicU64>>> =
+        RwLock::new(HashMap::new());
+}
+
+/// A cached, typed reference to a [`Dvar`]'s current value.
+///
+/// Reading through a handle doesn't take any locks, so handles are meant
+/// to be looked up once (e.g. at init) and then read as often as needed.
+/// Handles are cheap to clone.
+#[derive(Clone, Debug)]
+pub struct DvarHandle<T: HandleType> {
+    name: Arc<str>,
+    cell: Arc<AtomicU64>,
+    _type: PhantomData<T>,
+}
+
+impl<T: HandleType> DvarHandle<T> {
+    /// Returns the current value of the [`Dvar`].
+    pub fn get(&self) -> T {
+        T::from_bits(self.cell.load(Ordering::Acquire))
+    }
+
+    /// Returns the name of the [`Dvar`] this handle refers to.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Looks up the [`Dvar`] named `name` and returns a handle to its current
+/// value.
+///
+/// Code that registers a [`Dvar`] should keep the handle returned by
+/// [`register_bool`](super::register_bool) and friends instead, since
+/// their types are checked at compile time. This is for reading [`Dvar`]s
+/// registered elsewhere.
+///
+/// # Return Value
+///
+/// Returns [`Some`] if a [`Dvar`] with name `name` exists and its value is
+/// of type `T`, [`None`] otherwise.
+///
+/// # Panics
+/// Panics if the read lock for [`DVARS`] can't be acquired (usually because
+/// the write lock is held by a function farther up the call stack).
+///
+/// Example
+/// ```
+/// let onlinegame = handle::<bool>("onlinegame").unwrap();
+/// loop {
+///     if onlinegame.get() {
+///         // ...
+///     }
+/// }
+/// ```
+pub fn handle<T: HandleType>(name: &str) -> Option<DvarHandle<T>> {
+    // Hold the read lock until the cell is in CELLS, so that a concurrent
+    // set can't change the value without the cell seeing it
+    let dvars = DVARS.read().unwrap();
+    let value = T::from_value(&dvars.get(name)?.current)?;
+
+    let cell = CELLS
+        .write()
+        .unwrap()
+        .entry(name.to_owned())
+        .or_insert_with(|| Arc::new(AtomicU64::new(value.to_bits())))
+        .clone();
+    cell.store(value.to_bits(), Ordering::Release);
+
+    Some(DvarHandle {
+        name: Arc::from(name),
+        cell,
+        _type: PhantomData,
+    })
+}
+
+/// Returns a handle to the [`Dvar`] named `name`, which has just been
+/// registered with a value of type `T`.
+///
+/// Used by the `register_*` functions, so that a handle's type is checked
+/// against the type a [`Dvar`] is registered with at compile time.
+pub(super) fn for_registered<T: HandleType>(name: &str) -> DvarHandle<T> {
+    // Can't fail, since the Dvar was just registered as a `T`
+    handle(name).unwrap()
+}
+
+/// Updates the cell for the [`Dvar`] named `name` (if any handles to it
+/// have been created) with its new current value.
+///
+/// Must be called with the write lock for [`DVARS`] held, so that
+/// [`handle`] can't race with it.
+pub(super) fn sync(name: &str, value: &DvarValue) {
+    let cells = CELLS.read().unwrap();
+    let Some(cell) = cells.get(name) else {
+        return;
+    };
+
+    let bits = match value {
+        DvarValue::Bool(b) => b.to_bits(),
+        DvarValue::Int(i) => i.to_bits(),
+        DvarValue::Int64(i) => i.to_bits(),
+        DvarValue::Float(f) => HandleType::to_bits(*f),
+        // Handles can only be created for the types above, and a Dvar's
+        // type doesn't change once registered
+        _ => return,
+    };
+    cell.store(bits, Ordering::Release);
+}
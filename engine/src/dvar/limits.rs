@@ -7,7 +7,38 @@
 // DvarLimitsBool still needs to be defined for printing the domain
 
 use core::fmt::Display;
-use std::collections::HashSet;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// The domains without any custom-definable bounds are unit structs, which
+// some formats (e.g., TOML) can't represent. Serialize them as empty structs
+// instead.
+#[derive(Serialize, Deserialize)]
+struct EmptyDomain {}
+
+macro_rules! impl_serde_as_empty_domain {
+    ($($t:ty),*) => {
+        $(
+            impl Serialize for $t {
+                fn serialize<S: Serializer>(
+                    &self,
+                    serializer: S,
+                ) -> Result<S::Ok, S::Error> {
+                    EmptyDomain {}.serialize(serializer)
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $t {
+                fn deserialize<D: Deserializer<'de>>(
+                    deserializer: D,
+                ) -> Result<Self, D::Error> {
+                    EmptyDomain::deserialize(deserializer).map(|_| Self)
+                }
+            }
+        )*
+    };
+}
+
+impl_serde_as_empty_domain!(DvarLimitsBool, DvarLimitsString, DvarLimitsColor);
 
 /// Domain for [`Dvar`] with value type [`DvarValue::Bool`]
 ///
@@ -41,7 +72,7 @@ impl DvarLimitsBool {
 /// The domain is bounded by a custom-defined `min` and `max`,
 /// which may be any values representable by [`f32`] provided
 /// `min <= max`.
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct DvarLimitsFloat {
     pub min: f32,
     pub max: f32,
@@ -108,7 +139,7 @@ impl DvarLimitsFloat {
 /// which may be any values representable by [`f32`] provided
 /// `min <= max`. All elements of the vector share the domain
 /// (i.e., the domain cannot be defined on a per-element basis).
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct DvarLimitsVector2 {
     pub min: f32,
     pub max: f32,
@@ -187,7 +218,7 @@ impl DvarLimitsVector2 {
 /// which may be any values representable by [`f32`] provided
 /// `min <= max`. All elements of the vector share the domain
 /// (i.e., the domain cannot be defined on a per-element basis).
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct DvarLimitsVector3 {
     pub min: f32,
     pub max: f32,
@@ -266,7 +297,7 @@ impl DvarLimitsVector3 {
 /// which may be any values representable by [`f32`] provided
 /// `min <= max`. All elements of the vector share the domain
 /// (i.e., the domain cannot be defined on a per-element basis).
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct DvarLimitsVector4 {
     pub min: f32,
     pub max: f32,
@@ -344,7 +375,7 @@ impl DvarLimitsVector4 {
 /// The domain is bounded by a custom-defined `min` and `max`,
 /// which may be any values representable by [`i32`] provided
 /// `min <= max`.
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct DvarLimitsInt {
     pub min: i32,
     pub max: i32,
@@ -439,9 +470,12 @@ impl DvarLimitsString {
 /// The domain may consist of one or more different [`String`]s of
 /// any value, but it *must* at least contain at least the current
 /// value of the [`Dvar`].
-#[derive(Clone, Default, PartialEq, Eq, Debug)]
+///
+/// The strings are kept in the order they were supplied in, since
+/// enumerations can also be set by index into the domain.
+#[derive(Clone, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct DvarLimitsEnumeration {
-    pub strings: HashSet<String>,
+    pub strings: Vec<String>,
 }
 
 impl Display for DvarLimitsEnumeration {
@@ -461,7 +495,7 @@ impl DvarLimitsEnumeration {
     /// # Parameters
     /// * `domain` - A slice of [`String`]s containing the valid values for
     /// the [`Dvar`]. The [`Dvar`]'s initial value *must* be included in this
-    /// domain.
+    /// domain. Duplicates are dropped, otherwise the order is kept.
     ///
     /// # Panics
     /// Currently will panic if [`domain.is_empty()`]. Might be changed at some
@@ -480,9 +514,36 @@ impl DvarLimitsEnumeration {
             panic!("DvarLimitsEnumeration::new(): domain is empty.");
         }
 
-        Self {
-            strings: domain.iter().cloned().collect::<HashSet<_>>(),
+        let mut strings = Vec::with_capacity(domain.len());
+        for s in domain {
+            if !strings.contains(s) {
+                strings.push(s.clone());
+            }
         }
+
+        Self { strings }
+    }
+
+    /// Looks up a string in the domain, either by value or by its index in
+    /// the domain.
+    ///
+    /// # Return Value
+    ///
+    /// Returns the matching string from the domain, or [`None`] if `s` is
+    /// neither in the domain nor a valid index into it.
+    ///
+    /// # Example
+    /// ```
+    /// let domain = DvarLimitsEnumeration::new(&vec![
+    ///     "test".to_owned(),
+    ///     "test2".to_owned(),
+    /// ]);
+    /// assert_eq!(domain.resolve("1"), Some(&"test2".to_owned()));
+    /// ```
+    pub fn resolve(&self, s: &str) -> Option<&String> {
+        self.strings.iter().find(|e| *e == s).or_else(|| {
+            s.parse::<usize>().ok().and_then(|i| self.strings.get(i))
+        })
     }
 }
 
@@ -517,7 +578,7 @@ impl DvarLimitsColor {
 /// The domain is bounded by a custom-defined `min` and `max`,
 /// which may be any values representable by [`i64`] provided
 /// `min <= max`.
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct DvarLimitsInt64 {
     pub min: i64,
     pub max: i64,
@@ -582,7 +643,7 @@ impl DvarLimitsInt64 {
 /// which may be any values representable by [`f32`] provided
 /// `min <= max`. All elements of the vector share the domain
 /// (i.e., the domain cannot be defined on a per-element basis).
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct DvarLimitsLinearColorRGB {
     pub min: f32,
     pub max: f32,
@@ -666,7 +727,7 @@ impl DvarLimitsLinearColorRGB {
 /// which may be any values representable by [`f32`] provided
 /// `min <= max`. All elements of the vector share the domain
 /// (i.e., the domain cannot be defined on a per-element basis).
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct DvarLimitsColorXYZ {
     pub min: f32,
     pub max: f32,
@@ -745,7 +806,7 @@ impl DvarLimitsColorXYZ {
 }
 
 // Enum to tie all the DvarLimitsXXXX's together
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum DvarLimits {
     Bool(DvarLimitsBool),
     Float(DvarLimitsFloat),
@@ -782,6 +843,27 @@ impl Display for DvarLimits {
 }
 
 impl DvarLimits {
+    /// Checks that the domain can actually hold a value, i.e. that `min`
+    /// isn't greater than `max` for bounded domains, and that enumeration
+    /// domains aren't empty.
+    ///
+    /// The constructors already enforce this, but domains that come from
+    /// elsewhere (e.g. an imported file) might not.
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Self::Float(DvarLimitsFloat { min, max })
+            | Self::Vector2(DvarLimitsVector2 { min, max })
+            | Self::Vector3(DvarLimitsVector3 { min, max })
+            | Self::Vector4(DvarLimitsVector4 { min, max })
+            | Self::LinearColorRGB(DvarLimitsLinearColorRGB { min, max })
+            | Self::ColorXYZ(DvarLimitsColorXYZ { min, max }) => min <= max,
+            Self::Int(DvarLimitsInt { min, max }) => min <= max,
+            Self::Int64(DvarLimitsInt64 { min, max }) => min <= max,
+            Self::Enumeration(e) => !e.strings.is_empty(),
+            Self::Bool(_) | Self::String(_) | Self::Color(_) => true,
+        }
+    }
+
     // A bunch of helper functions to extract the domain
     // Useful if a given Dvar is known to be a specific type
     // Otherwise long match expressions would be required
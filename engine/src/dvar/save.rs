@@ -0,0 +1,167 @@
+// This file implements the Dvar half of save games: capturing the values of
+// every SAVED Dvar into a snapshot, writing it into a save file, and loading
+// it back so that `restore_dvars` can apply it.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+use super::{value::DvarValue, DvarFlags, DVARS};
+
+/// Identifies the start of a snapshot blob, so that loading a file that
+/// isn't one fails cleanly.
+const SNAPSHOT_MAGIC: &[u8; 8] = b"DVARSAVE";
+
+/// The values of every [`DvarFlags::SAVED`] [`Dvar`] at a point in time.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DvarSnapshot {
+    dvars: Vec<(String, DvarValue)>,
+}
+
+impl DvarSnapshot {
+    /// Captures the current value of every [`DvarFlags::SAVED`] [`Dvar`].
+    ///
+    /// The captured values also become the [`Dvar`]s' saved values.
+    ///
+    /// # Panics
+    /// Panics if the write lock for [`DVARS`] can't be acquired (usually
+    /// because the write lock or a read lock is held by a function farther
+    /// up the call stack).
+    pub fn capture() -> Self {
+        let mut dvars = DVARS
+            .write()
+            .unwrap()
+            .values_mut()
+            .filter(|d| d.flags.contains(DvarFlags::SAVED))
+            .map(|d| {
+                d.saved = d.current.clone();
+                (d.name.clone(), d.current.clone())
+            })
+            .collect::<Vec<_>>();
+        dvars.sort_by(|a, b| a.0.cmp(&b.0));
+        Self { dvars }
+    }
+
+    /// Returns the number of [`Dvar`]s in the snapshot.
+    pub fn len(&self) -> usize {
+        self.dvars.len()
+    }
+
+    /// Returns `true` if the snapshot doesn't contain any [`Dvar`]s.
+    pub fn is_empty(&self) -> bool {
+        self.dvars.is_empty()
+    }
+
+    /// Serializes the snapshot into a blob that can be embedded in a save
+    /// file.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = SNAPSHOT_MAGIC.to_vec();
+        // Serializing a Vec of Strings and DvarValues can't fail
+        bytes.extend(serde_json::to_vec(self).unwrap());
+        bytes
+    }
+
+    /// Deserializes a snapshot from a blob created by
+    /// [`DvarSnapshot::to_bytes`].
+    ///
+    /// Returns [`Err`] if `bytes` isn't a valid snapshot.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ()> {
+        let Some(json) = bytes.strip_prefix(SNAPSHOT_MAGIC) else {
+            com::println!(
+                console::Channel::ERROR,
+                "dvar::DvarSnapshot::from_bytes: not a dvar snapshot"
+            );
+            return Err(());
+        };
+
+        serde_json::from_slice(json).map_err(|e| {
+            com::println!(
+                console::Channel::ERROR,
+                "dvar::DvarSnapshot::from_bytes: {}",
+                e
+            );
+        })
+    }
+
+    /// Loads the snapshot's values into the [`Dvar`]s' saved values and
+    /// marks them as loaded from a save game, so that the next
+    /// `restoreDvars` applies them.
+    ///
+    /// Values for [`Dvar`]s that don't exist, aren't
+    /// [`DvarFlags::SAVED`], or have changed type are skipped.
+    ///
+    /// # Return Value
+    ///
+    /// Returns the number of [`Dvar`]s loaded.
+    ///
+    /// # Panics
+    /// Panics if the write lock for [`DVARS`] can't be acquired (usually
+    /// because the write lock or a read lock is held by a function farther
+    /// up the call stack).
+    pub fn apply(&self) -> usize {
+        let mut writer = DVARS.write().unwrap();
+        let mut count = 0;
+        for (name, value) in &self.dvars {
+            let Some(d) = writer.get_mut(name) else {
+                com::warnln!(
+                    console::Channel::SYSTEM,
+                    "saved dvar \'{}\' doesn\'t exist, skipping",
+                    name
+                );
+                continue;
+            };
+
+            if !d.flags.contains(DvarFlags::SAVED)
+                || core::mem::discriminant(&d.current)
+                    != core::mem::discriminant(value)
+            {
+                com::warnln!(
+                    console::Channel::SYSTEM,
+                    "saved dvar \'{}\' can\'t be restored, skipping",
+                    name
+                );
+                continue;
+            }
+
+            d.saved = value.clone();
+            d.loaded_from_save_game = true;
+            count += 1;
+        }
+
+        count
+    }
+}
+
+/// Captures a [`DvarSnapshot`] and writes it to `path`.
+///
+/// # Return Value
+///
+/// Returns the number of [`Dvar`]s written on success.
+pub fn write_saved_dvars(path: impl AsRef<Path>) -> Result<usize, ()> {
+    let snapshot = DvarSnapshot::capture();
+    match fs::write_file(&path, &snapshot.to_bytes()) {
+        Ok(_) => Ok(snapshot.len()),
+        Err(_) => Err(()),
+    }
+}
+
+/// Reads a [`DvarSnapshot`] from `path` and applies it (see
+/// [`DvarSnapshot::apply`]).
+///
+/// # Return Value
+///
+/// Returns the number of [`Dvar`]s loaded on success.
+pub fn load_saved_dvars(path: impl AsRef<Path>) -> Result<usize, ()> {
+    let Ok(file) = fs::read_file(&path) else {
+        com::println!(
+            console::Channel::FILES,
+            "Couldn't read saved dvars from {}",
+            path.as_ref().display()
+        );
+        return Err(());
+    };
+
+    DvarSnapshot::from_bytes(&file).map(|snapshot| snapshot.apply())
+}
@@ -1,9 +1,10 @@
 use core::fmt::Display;
+use serde::{Deserialize, Serialize};
 
 use crate::common::{Vec2f32, Vec3f32, Vec4f32};
 
 // Enum to hold all possible Dvar values
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum DvarValue {
     Bool(bool),
     Float(f32),
@@ -34,7 +35,9 @@ impl Display for DvarValue {
             Self::Int(i) => write!(f, "{}", i),
             Self::String(s) => write!(f, "{}", s),
             Self::Enumeration(s) => write!(f, "{}", s),
-            Self::Color(c) => write!(f, "({}, {}, {})", c.0, c.1, c.2),
+            Self::Color(c) => {
+                write!(f, "({}, {}, {}, {})", c.0, c.1, c.2, c.3)
+            }
             Self::Int64(i) => write!(f, "{}", i),
             Self::LinearColorRGB(c) => write!(f, "({}, {}, {})", c.0, c.1, c.2),
             Self::ColorXYZ(c) => write!(f, "({}, {}, {})", c.0, c.1, c.2),
@@ -43,6 +46,24 @@ impl Display for DvarValue {
 }
 
 impl DvarValue {
+    /// Returns a human-readable name for the type of the value.
+    pub const fn type_name(&self) -> &'static str {
+        match self {
+            Self::Bool(_) => "bool",
+            Self::Float(_) => "float",
+            Self::Vector2(_) => "vec2",
+            Self::Vector3(_) => "vec3",
+            Self::Vector4(_) => "vec4",
+            Self::Int(_) => "int",
+            Self::String(_) => "string",
+            Self::Enumeration(_) => "enum",
+            Self::Color(_) => "color",
+            Self::Int64(_) => "int64",
+            Self::LinearColorRGB(_) => "linear color rgb",
+            Self::ColorXYZ(_) => "color xyz",
+        }
+    }
+
     // Helper functions defined for the same reason as in DvarLimits
     pub const fn as_bool(&self) -> Option<bool> {
         match self {
@@ -8,10 +8,16 @@ use crate::{
 };
 use arrayvec::ArrayVec;
 use cfg_if::cfg_if;
-use core::{str::FromStr, sync::atomic::AtomicUsize};
+use core::{
+    str::FromStr,
+    sync::atomic::{AtomicI32, AtomicUsize},
+};
+use flate2::Crc;
 use std::{
+    collections::{hash_map::RandomState, HashSet},
     ffi::OsStr,
-    io::{Read, Seek, Write},
+    hash::{BuildHasher, Hasher},
+    io::{Cursor, Read, Seek, Write},
     ops::{Deref, DerefMut},
     path::{Path, PathBuf},
     sync::RwLock,
@@ -231,8 +237,111 @@ pub fn init_filesystem(dev: bool) {
     startup("main", dev);
 }
 
-fn display_path(_pure: bool) {
-    todo!()
+/// Prints every [`Searchpath`] in [`FS_SEARCHPATHS`], followed by every
+/// open file handle.
+///
+/// If `cull_unused` is set, [`Searchpath`]s that aren't used (i.e. that are
+/// for a different language) are skipped.
+fn display_path(cull_unused: bool) {
+    com::println!(
+        console::Channel::DONT_FILTER,
+        "Current language: {}",
+        seh::get_current_language()
+    );
+    com::println!(
+        console::Channel::DONT_FILTER,
+        "Current fs_basepath: {}",
+        dvar::get_string("fs_basepath").unwrap()
+    );
+    com::println!(
+        console::Channel::DONT_FILTER,
+        "Current fs_homepath: {}",
+        dvar::get_string("fs_homepath").unwrap()
+    );
+    if dvar::get_bool("fs_ignoreLocalized").unwrap() {
+        com::println!(
+            console::Channel::DONT_FILTER,
+            "    localized assets are being ignored"
+        );
+    }
+
+    com::println!(console::Channel::DONT_FILTER, "Current search path:");
+    for sp in FS_SEARCHPATHS.read().unwrap().iter() {
+        if cull_unused && !use_searchpath(sp) {
+            continue;
+        }
+
+        match &sp.qdir {
+            Qdir::Iwd { iwd, iwd_name, .. } => {
+                let Some(iwd) = iwd else {
+                    com::println!(
+                        console::Channel::DONT_FILTER,
+                        "{} (failed to load)",
+                        iwd_name.display()
+                    );
+                    continue;
+                };
+
+                let iwd = iwd.read().unwrap();
+                com::println!(
+                    console::Channel::DONT_FILTER,
+                    "{} ({} files)",
+                    iwd_name.display(),
+                    iwd.file_names().filter(|f| !f.ends_with('/')).count()
+                );
+                if let Some(lang) = sp.language {
+                    com::println!(
+                        console::Channel::DONT_FILTER,
+                        "    localized assets iwd file for {}",
+                        lang
+                    );
+                }
+                if FS_NUM_SERVER_IWDS.load_relaxed() != 0 {
+                    let s = if iwd_is_pure(sp) {
+                        "on"
+                    } else {
+                        "not on"
+                    };
+                    com::println!(
+                        console::Channel::DONT_FILTER,
+                        "    {} the pure list",
+                        s
+                    );
+                }
+            }
+            Qdir::Dir { dir } => {
+                com::println!(
+                    console::Channel::DONT_FILTER,
+                    "{}",
+                    build_os_path(&dir.path, Some(&dir.gamedir), "")
+                        .display()
+                );
+                if let Some(lang) = sp.language {
+                    com::println!(
+                        console::Channel::DONT_FILTER,
+                        "    localized assets game folder for {}",
+                        lang
+                    );
+                }
+            }
+        }
+
+        if sp.ignore {
+            com::println!(console::Channel::DONT_FILTER, "    ignored");
+        }
+    }
+
+    com::println!(console::Channel::DONT_FILTER, "\nFile Handles:");
+    for (i, fh) in FSH.read().unwrap().iter().enumerate() {
+        if let Some(fh) = fh {
+            com::println!(
+                console::Channel::DONT_FILTER,
+                "handle {}: {}",
+                i,
+                fh.name().display()
+            );
+        }
+    }
 }
 
 fn path_f() {
@@ -250,9 +359,174 @@ fn dir_f() {
             console::Channel::DONT_FILTER,
             "usage: dir <directory> [extension]"
         );
+        return;
+    }
+
+    let path = cmd::argv(1);
+    let extension = cmd::argv(2);
+
+    com::println!(
+        console::Channel::DONT_FILTER,
+        "Directory of {} {}",
+        path,
+        extension
+    );
+    com::println!(console::Channel::DONT_FILTER, "---------------");
+
+    let files = list_files(&path, &extension);
+    for file in &files {
+        com::println!(console::Channel::DONT_FILTER, "{}", file);
     }
+}
+
+/// Collects the paths of every file under `dir` (and of every subdirectory,
+/// with a trailing '/'), relative to `root` and with '/' as the separator.
+///
+/// Subdirectories are only descended into if `recursive` is set.
+fn list_dir(root: &Path, dir: &Path, recursive: bool, files: &mut Vec<String>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
 
-    todo!()
+        if path.is_dir() {
+            files.push(format!("{}/", relative));
+            if recursive {
+                list_dir(root, &path, recursive, files);
+            }
+        } else {
+            files.push(relative);
+        }
+    }
+}
+
+/// Calls `f` with the path of every file (and directory, with a trailing
+/// '/') in every used [`Searchpath`], in search order.
+///
+/// Paths are relative to the game directory, and use '/' as the separator.
+/// Only paths under `subdir` are guaranteed to be passed to `f`, and unless
+/// `recursive` is set, only those directly inside of it.
+fn for_each_file(subdir: &str, recursive: bool, mut f: impl FnMut(&str)) {
+    for sp in FS_SEARCHPATHS.read().unwrap().iter() {
+        if sp.ignore || !use_searchpath(sp) {
+            continue;
+        }
+
+        match &sp.qdir {
+            Qdir::Dir { dir } => {
+                let root = build_os_path(&dir.path, Some(&dir.gamedir), "");
+                let mut files = Vec::new();
+                list_dir(&root, &root.join(subdir), recursive, &mut files);
+                files.iter().for_each(|file| f(file));
+            }
+            Qdir::Iwd { iwd: Some(iwd), .. } => {
+                iwd.read().unwrap().file_names().for_each(&mut f);
+            }
+            Qdir::Iwd { iwd: None, .. } => {}
+        }
+    }
+}
+
+/// Returns `s` without `prefix`, ignoring case, or [`None`] if `s` doesn't
+/// start with `prefix`.
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let start = s.get(..prefix.len())?;
+    if start.eq_ignore_ascii_case(prefix) {
+        s.get(prefix.len()..)
+    } else {
+        None
+    }
+}
+
+/// Runs [`for_each_file`] and collects whatever `select` returns for each
+/// path.
+///
+/// When the same name (ignoring case) is selected more than once, the one
+/// from the [`Searchpath`] searched first wins. The returned list is sorted
+/// (ignoring case).
+fn collect_files(
+    subdir: &str,
+    recursive: bool,
+    mut select: impl FnMut(&str) -> Option<String>,
+) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut files = Vec::new();
+
+    for_each_file(subdir, recursive, |file| {
+        if let Some(name) = select(file) {
+            if seen.insert(name.to_ascii_lowercase()) {
+                files.push(name);
+            }
+        }
+    });
+
+    files.sort_unstable_by_key(|f| f.to_ascii_lowercase());
+    files
+}
+
+/// Lists the files directly inside of the directory `path` (relative to the
+/// game directory) across every [`Searchpath`], both loose files and files
+/// inside of IWDs.
+///
+/// If `extension` isn't empty, only files with that extension (with or
+/// without the leading '.') are listed. If `extension` is `/`,
+/// subdirectories are listed instead of files.
+///
+/// [`Searchpath`]s that are ignored or for a different language (see
+/// [`use_searchpath`]) are skipped. The returned names are relative to
+/// `path`, sorted, and free of duplicates.
+///
+/// # Example
+/// ```
+/// // ["config_mp.cfg", "default_mp.cfg", ...]
+/// let configs = fs::list_files("", "cfg");
+/// // ["mp_array.d3dbsp", "mp_nuked.d3dbsp", ...]
+/// let maps = fs::list_files("maps/mp", "d3dbsp");
+/// ```
+pub fn list_files(path: &str, extension: &str) -> Vec<String> {
+    let path = path.replace('\\', "/");
+    let path = path.trim_matches('/');
+    let prefix = if path.is_empty() {
+        String::new()
+    } else {
+        format!("{}/", path)
+    };
+    let dirs_only = extension == "/";
+    let extension = extension.trim_start_matches('.');
+
+    collect_files(path, false, |file| {
+        let name = strip_prefix_ignore_case(file, &prefix)?;
+        if dirs_only {
+            let (dir, _) = name.split_once('/')?;
+            return Some(dir.to_owned());
+        }
+
+        if name.is_empty() || name.contains('/') {
+            return None;
+        }
+        let matches_extension = extension.is_empty()
+            || Path::new(name)
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case(extension));
+        matches_extension.then(|| name.to_owned())
+    })
+}
+
+/// Lists every file in [`FS_SEARCHPATHS`] (both loose files and files
+/// inside of IWDs) whose path matches `filter` (see [`com::filter`]).
+///
+/// Follows the same rules as [`list_files`], but searches every directory.
+pub fn list_filtered_files(filter: &str) -> Vec<String> {
+    collect_files("", true, |file| {
+        let matches = !file.ends_with('/') && com::filter(filter, file, false);
+        matches.then(|| file.to_owned())
+    })
 }
 
 fn new_dir_f() {
@@ -266,7 +540,17 @@ fn new_dir_f() {
         return;
     }
 
-    todo!()
+    let filter = cmd::argv(1);
+    com::println!(console::Channel::DONT_FILTER, "---------------");
+    let files = list_filtered_files(&filter);
+    for file in &files {
+        com::println!(console::Channel::DONT_FILTER, "{}", file);
+    }
+    com::println!(
+        console::Channel::DONT_FILTER,
+        "{} files listed",
+        files.len()
+    );
 }
 
 fn touch_file_f() {
@@ -327,7 +611,7 @@ fn startup(gamedir: impl AsRef<Path>, _dev: bool) {
     }
 
     let basegame = dvar::get_string("fs_basegame").unwrap();
-    let gamedir_var = dvar::get_string("fs_gameDirVar").unwrap();
+    let gamedir_var = dvar::get_string("fs_game").unwrap();
 
     if !basegame.is_empty()
         && gamedir.as_ref() == Path::new("main")
@@ -348,7 +632,7 @@ fn startup(gamedir: impl AsRef<Path>, _dev: bool) {
 
     add_commands();
     path_f();
-    dvar::clear_modified("fs_gameDirVar").unwrap();
+    dvar::clear_modified("fs_game").unwrap();
     com::println!(console::Channel::FILES, "-----------------------");
     com::println!(
         console::Channel::FILES,
@@ -358,6 +642,77 @@ fn startup(gamedir: impl AsRef<Path>, _dev: bool) {
 }
 
 fn register_dvars() {
+    let basepath = sys::cwd().to_string_lossy().into_owned();
+    dvar::register_bool(
+        "fs_restrict",
+        false,
+        dvar::DvarFlags::WRITE_PROTECTED,
+        Some("Restrict file access for demos etc."),
+    )
+    .unwrap();
+    dvar::register_bool(
+        "fs_usedevdir",
+        false,
+        dvar::DvarFlags::WRITE_PROTECTED,
+        Some("Use development directories."),
+    )
+    .unwrap();
+    dvar::register_int(
+        "fs_debug",
+        0,
+        Some(0),
+        Some(2),
+        dvar::DvarFlags::empty(),
+        Some("Enable file system debugging information"),
+    )
+    .unwrap();
+    dvar::register_bool(
+        "fs_copyfiles",
+        false,
+        dvar::DvarFlags::WRITE_PROTECTED,
+        Some("Copy all used files to another location"),
+    )
+    .unwrap();
+    dvar::register_string(
+        "fs_cdpath",
+        sys::default_cd_path(),
+        dvar::DvarFlags::WRITE_PROTECTED,
+        Some("CD path"),
+    )
+    .unwrap();
+    dvar::register_string(
+        "fs_basepath",
+        &basepath,
+        dvar::DvarFlags::WRITE_PROTECTED,
+        Some("Base game path"),
+    )
+    .unwrap();
+    dvar::register_string(
+        "fs_basegame",
+        "",
+        dvar::DvarFlags::WRITE_PROTECTED,
+        Some("Base game name"),
+    )
+    .unwrap();
+    dvar::register_string(
+        "fs_homepath",
+        &basepath,
+        dvar::DvarFlags::WRITE_PROTECTED,
+        Some("Game home path"),
+    )
+    .unwrap();
+    dvar::register_string(
+        "fs_game",
+        "",
+        dvar::DvarFlags::SYSTEM_INFO
+            | dvar::DvarFlags::SERVER_INFO
+            | dvar::DvarFlags::WRITE_PROTECTED,
+        Some(
+            "Game data directory. Must be \"\" or a sub directory of \
+             'mods/'.",
+        ),
+    )
+    .unwrap();
     dvar::register_bool(
         "fs_ignoreLocalized",
         false,
@@ -475,14 +830,11 @@ type Iwd = ZipArchive<std::fs::File>;
 
 enum Qfile {
     ZipFile {
-        /// Ref-counted to allow [`Qdir`] and [`Searchpath`] to use the same
-        /// archive.
-        archive: Arc<RwLock<Iwd>>,
-
-        // Have to embed this here to make our implementation of Read work.
-        // Can't embed the ZipFile instead, since it's only valid for as long
-        // as the archive's RwLock is locked.
-        /// Path associated with [`Qfile::ZipFile::archive`].
+        /// The entry's contents, decompressed when the file was opened, since
+        /// entries can't be seeked and are only readable while the archive's
+        /// RwLock is locked.
+        data: Cursor<Vec<u8>>,
+        /// Path of the entry within its IWD.
         name: PathBuf,
     },
     File {
@@ -494,11 +846,7 @@ enum Qfile {
 impl Read for Qfile {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         match self {
-            Qfile::ZipFile { archive, name } => archive
-                .write()
-                .unwrap()
-                .by_name(&name.to_string_lossy())?
-                .read(buf),
+            Qfile::ZipFile { data, .. } => data.read(buf),
             Qfile::File { ref mut file, .. } => file.read(buf),
         }
     }
@@ -648,6 +996,14 @@ enum Qdir {
         /// archive.
         iwd: Option<Arc<RwLock<Iwd>>>,
         iwd_name: PathBuf,
+        /// Checksum of the IWD's contents (see [`iwd_checksums`]).
+        checksum: i32,
+        /// Like [`Qdir::Iwd::checksum`], but seeded with
+        /// [`FS_CHECKSUM_FEED`] so it can't be precomputed.
+        pure_checksum: i32,
+        /// Whether a file has been read from the IWD since the last call to
+        /// [`clear_iwd_references`].
+        referenced: AtomicBool,
     },
     Dir {
         dir: Directory,
@@ -756,6 +1112,54 @@ fn load_zip_file(
     r
 }
 
+/// Computes the checksum and pure checksum of `iwd`.
+///
+/// Both are CRC-32s of the CRC-32s of every non-empty file in the IWD, as
+/// listed in its central directory, so they change if any file's contents
+/// do. The pure checksum is additionally seeded with [`FS_CHECKSUM_FEED`],
+/// so that a client can't just replay checksums it got from somewhere else.
+#[allow(clippy::cast_possible_wrap)]
+fn iwd_checksums(iwd: &mut Iwd) -> (i32, i32) {
+    let mut crcs = Vec::with_capacity(iwd.len() * 4);
+    for i in 0..iwd.len() {
+        // Raw, so nothing gets decompressed
+        let Ok(file) = iwd.by_index_raw(i) else {
+            continue;
+        };
+        if file.size() > 0 {
+            crcs.extend_from_slice(&file.crc32().to_le_bytes());
+        }
+    }
+
+    let mut checksum = Crc::new();
+    checksum.update(&crcs);
+
+    let mut pure_checksum = Crc::new();
+    pure_checksum.update(&FS_CHECKSUM_FEED.load_relaxed().to_le_bytes());
+    pure_checksum.update(&crcs);
+
+    (checksum.sum() as i32, pure_checksum.sum() as i32)
+}
+
+/// Loads the IWD at `filename` (see [`load_zip_file`]) and computes its
+/// checksums.
+///
+/// If the IWD can't be loaded, the returned [`Qdir`] has no archive, and
+/// both checksums are 0.
+fn load_iwd_qdir(filename: impl AsRef<Path>, iwd_name: PathBuf) -> Qdir {
+    let mut iwd = load_zip_file(&filename, &iwd_name).ok();
+    let (checksum, pure_checksum) =
+        iwd.as_mut().map_or((0, 0), iwd_checksums);
+
+    Qdir::Iwd {
+        iwd: iwd.map(|i| Arc::new(RwLock::new(i))),
+        iwd_name,
+        checksum,
+        pure_checksum,
+        referenced: AtomicBool::new(false),
+    }
+}
+
 const MAX_IWD_FILES_IN_GAME_DIRECTORY: usize = 1024;
 
 /// Attempts to parse the language from an IWD file's name.
@@ -858,14 +1262,11 @@ fn add_iwd_files_for_game_directory(
 
                     let filename =
                         build_os_path(&base, Some(&gamedir), &iwd_name);
-                    let iwd = load_zip_file(&filename, &iwd_name)
-                        .ok()
-                        .map(|i| Arc::new(RwLock::new(i)));
                     let sp = Searchpath {
                         ignore: false,
                         ignore_pure_check: false,
                         language: Some(lang),
-                        qdir: Qdir::Iwd { iwd, iwd_name },
+                        qdir: load_iwd_qdir(filename, iwd_name),
                     };
                     add_searchpath(sp);
                 } else {
@@ -917,14 +1318,11 @@ fn add_iwd_files_for_game_directory(
             );
         } else {
             let filename = build_os_path(&base, Some(&gamedir), &iwd_name);
-            let iwd = load_zip_file(&filename, &iwd_name)
-                .ok()
-                .map(|i| Arc::new(RwLock::new(i)));
             let sp = Searchpath {
                 ignore: false,
                 ignore_pure_check: false,
                 language: None,
-                qdir: Qdir::Iwd { iwd, iwd_name },
+                qdir: load_iwd_qdir(filename, iwd_name),
             };
             add_searchpath(sp);
         }
@@ -940,13 +1338,10 @@ fn add_game_directory(
     lang: Option<locale::Language>,
 ) -> std::io::Result<()> {
     let is_language_dir = lang.is_some();
-    let gamedir = if is_language_dir {
-        gamedir.as_ref().to_path_buf()
+    let gamedir = if let Some(lang) = lang {
+        gamedir.as_ref().to_path_buf().join(lang.to_string())
     } else {
-        gamedir
-            .as_ref()
-            .to_path_buf()
-            .join(lang.unwrap().to_string())
+        gamedir.as_ref().to_path_buf()
     };
 
     for sp in FS_SEARCHPATHS.read().unwrap().iter() {
@@ -979,7 +1374,7 @@ fn add_game_directory(
         }
     }
 
-    if is_language_dir {
+    if !is_language_dir {
         *FS_GAMEDIR.write().unwrap() = gamedir.clone();
     } else {
         let dir = build_os_path(&base, Some(&gamedir), "");
@@ -1051,13 +1446,157 @@ fn pure_ignore_files(filename: impl AsRef<Path>) -> bool {
     }
 }
 
-// TODO - implement
-fn iwd_is_pure(_iwd: &Iwd) -> bool {
-    true
+/// Checks whether the pure check is being enforced, i.e. whether `sv_pure`
+/// is set and the server has sent its list of IWDs (see
+/// [`set_pure_server_iwds`]).
+///
+/// Since nothing sends that list yet, this is always `false` for now.
+fn pure_mode() -> bool {
+    FS_NUM_SERVER_IWDS.load_relaxed() != 0
+        && dvar::get_bool("sv_pure").unwrap_or(false)
+}
+
+/// Checks whether files may be read from `sp`.
+///
+/// Only IWDs can be impure. In pure mode (see [`pure_mode`]), an IWD is
+/// pure if its checksum is on the server's list. Otherwise, every IWD is
+/// pure.
+fn iwd_is_pure(sp: &Searchpath) -> bool {
+    let Qdir::Iwd { checksum, .. } = &sp.qdir else {
+        return true;
+    };
+
+    !pure_mode() || FS_SERVER_IWD_CHECKSUMS.read().unwrap().contains(checksum)
+}
+
+/// Marks the IWD `sp` as referenced, so that its checksums are included in
+/// [`referenced_iwd_checksums`] and [`referenced_iwd_pure_checksums`].
+fn add_iwd_pure_check_reference(sp: &Searchpath) {
+    if let Qdir::Iwd { referenced, .. } = &sp.qdir {
+        referenced.store_relaxed(true);
+    }
+}
+
+/// Sets the checksums of the IWDs the server has loaded, i.e. the only
+/// IWDs files may be read from while `sv_pure` is set.
+///
+/// An empty list disables the pure check (e.g. when disconnecting, or
+/// connecting to a server that isn't pure).
+///
+/// TODO - nothing calls this yet. The client is supposed to pass the list
+/// (see [`loaded_iwd_checksums`]) once it gets it from the server, which
+/// needs networking. Until then, the pure check is never enforced.
+pub fn set_pure_server_iwds(checksums: &[i32]) {
+    *FS_SERVER_IWD_CHECKSUMS.write().unwrap() = checksums.to_vec();
+    FS_NUM_SERVER_IWDS.store_relaxed(checksums.len());
+
+    if !checksums.is_empty() {
+        com::dprintln!(
+            console::Channel::FILES,
+            "Connected to a pure server with {} IWDs.",
+            checksums.len()
+        );
+    }
+}
+
+/// Returns a random, nonzero checksum for [`FS_FAKE_CHK_SUM`].
+fn fake_checksum() -> usize {
+    let random = RandomState::new().build_hasher().finish();
+    (random as u32 | 1) as usize
 }
 
-// TODO - implement
-fn add_iwd_pure_check_reference(_sp: &Searchpath) {}
+/// Sets the value [`Qdir::Iwd::pure_checksum`]s are seeded with.
+///
+/// The pure checksums are only computed when IWDs are loaded, so this has
+/// to be called before [`init_filesystem`] to have any effect. [`sv::init`]
+/// does so at startup.
+pub fn set_checksum_feed(feed: i32) {
+    FS_CHECKSUM_FEED.store_relaxed(feed);
+}
+
+/// Returns the checksum and pure checksum of every IWD in use (or only
+/// those that have been referenced, if `referenced_only` is set), in search
+/// order.
+fn iwd_checksum_list(referenced_only: bool) -> Vec<(i32, i32)> {
+    FS_SEARCHPATHS
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|sp| use_searchpath(sp))
+        .filter_map(|sp| match &sp.qdir {
+            Qdir::Iwd {
+                iwd: Some(_),
+                checksum,
+                pure_checksum,
+                referenced,
+                ..
+            } if !referenced_only || referenced.load_relaxed() => {
+                Some((*checksum, *pure_checksum))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn join_checksums(checksums: impl IntoIterator<Item = i32>) -> String {
+    checksums.into_iter().map(|c| format!("{} ", c)).collect()
+}
+
+/// Returns the checksums of every IWD in use, separated by spaces. This is
+/// what a pure server sends to clients for [`set_pure_server_iwds`].
+pub fn loaded_iwd_checksums() -> String {
+    join_checksums(iwd_checksum_list(false).into_iter().map(|(c, _)| c))
+}
+
+/// Returns the pure checksums of every IWD in use, separated by spaces.
+pub fn loaded_iwd_pure_checksums() -> String {
+    join_checksums(iwd_checksum_list(false).into_iter().map(|(_, c)| c))
+}
+
+/// Returns the checksums of every IWD that files have been read from,
+/// separated by spaces.
+pub fn referenced_iwd_checksums() -> String {
+    join_checksums(iwd_checksum_list(true).into_iter().map(|(c, _)| c))
+}
+
+/// Returns the pure checksums of every IWD that files have been read from,
+/// separated by spaces, for a client to send to a pure server.
+///
+/// If a file has been read from outside of an IWD, a fake checksum is
+/// included, so that the server rejects the client. The last number encodes
+/// the rest of the list, so that the list can't just be edited.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_possible_wrap,
+    clippy::as_conversions
+)]
+pub fn referenced_iwd_pure_checksums() -> String {
+    let mut checksums = iwd_checksum_list(true)
+        .into_iter()
+        .map(|(_, c)| c)
+        .collect::<Vec<_>>();
+    let encoded = checksums
+        .iter()
+        .fold(FS_CHECKSUM_FEED.load_relaxed(), |acc, c| acc ^ c)
+        ^ checksums.len() as i32;
+
+    let fake_checksum = FS_FAKE_CHK_SUM.load_relaxed();
+    if fake_checksum != 0 {
+        checksums.push(fake_checksum as i32);
+    }
+    checksums.push(encoded);
+    join_checksums(checksums)
+}
+
+/// Forgets which IWDs have been referenced (e.g. when a new map is loaded).
+pub fn clear_iwd_references() {
+    for sp in FS_SEARCHPATHS.read().unwrap().iter() {
+        if let Qdir::Iwd { referenced, .. } = &sp.qdir {
+            referenced.store_relaxed(false);
+        }
+    }
+    FS_FAKE_CHK_SUM.store_relaxed(0);
+}
 
 fn files_are_loaded_globally(filename: impl AsRef<Path>) -> bool {
     const EXTS: [&'static str; 7] = [
@@ -1071,7 +1610,9 @@ fn files_are_loaded_globally(filename: impl AsRef<Path>) -> bool {
     ];
 
     if let Some(ext) = filename.as_ref().extension() {
-        EXTS.iter().find(|&e| e == &ext).is_some()
+        // [`Path::extension`] doesn't include the leading '.'
+        EXTS.iter()
+            .any(|e| OsStr::new(e.trim_start_matches('.')) == ext)
     } else {
         false
     }
@@ -1104,14 +1645,19 @@ fn file_write(file: &mut impl Write, data: &[u8]) -> std::io::Result<usize> {
 lazy_static! {
     static ref FS_NUM_SERVER_IWDS: AtomicUsize = AtomicUsize::new(0);
     static ref FS_FAKE_CHK_SUM: AtomicUsize = AtomicUsize::new(0);
+    static ref FS_SERVER_IWD_CHECKSUMS: RwLock<Vec<i32>> =
+        RwLock::new(Vec::new());
 }
 
+static FS_CHECKSUM_FEED: AtomicI32 = AtomicI32::new(0);
+
 fn open_file_read_for_thread(
     filename: impl AsRef<Path>,
     thread: Thread,
 ) -> Result<(Fd, u64), std::io::Error> {
     let mut b = false;
-    let impure_iwd = false;
+    // IWD the file was found in, but couldn't be read from since it's impure
+    let mut impure_iwd = None;
 
     let fd = handle_for_file(thread)?;
 
@@ -1124,7 +1670,7 @@ fn open_file_read_for_thread(
             Qdir::Dir { dir } => {
                 if (sp.ignore == false
                     && dvar::get_bool("fs_restrict").unwrap() == false
-                    && FS_NUM_SERVER_IWDS.load_relaxed() == 0)
+                    && !pure_mode())
                     || (sp.is_localized()
                         || sp.ignore_pure_check
                         || pure_ignore_files(&filename))
@@ -1135,18 +1681,26 @@ fn open_file_read_for_thread(
                         &filename,
                         thread,
                     );
-                    let file = file_open_read(&ospath)?;
+                    let Ok(mut file) = file_open_read(&ospath) else {
+                        continue;
+                    };
+                    let file_size = file_get_file_size(&mut file)?;
+                    file.rewind()?;
                     let fh = FileHandleData {
                         file: Qfile::File {
                             file,
                             name: filename.as_ref().to_path_buf(),
                         },
                         handle_sync: false,
-                        file_size: 0,
+                        file_size: file_size as _,
                         streamed: false,
                     };
                     FSH.write().unwrap()[fd.as_usize()] = Some(fh);
-                    // fake check sum
+                    // Let a pure server know that something was read from
+                    // outside of an IWD
+                    if !files_are_loaded_globally(&filename) {
+                        FS_FAKE_CHK_SUM.store_relaxed(fake_checksum());
+                    }
                     if dvar::get_int("fs_debug").unwrap() != 0 {
                         com::println!(
                             console::Channel::FILES,
@@ -1176,6 +1730,8 @@ fn open_file_read_for_thread(
                         let size = copy_file(ospath, ospath_dest)?;
                         return Ok((fd, size));
                     }
+
+                    return Ok((fd, file_size));
                 } else if b == false {
                     let ospath = build_os_path_for_thread(
                         &dir.path,
@@ -1186,19 +1742,37 @@ fn open_file_read_for_thread(
                     b = file_open_read(ospath).is_ok();
                 }
             }
-            Qdir::Iwd { iwd, iwd_name } => {
+            Qdir::Iwd { iwd, iwd_name, .. } => {
                 if let Some(ref iwd) = iwd {
                     let mut archive = iwd.write().unwrap();
-                    if let Ok(zip_file) =
+                    if let Ok(mut zip_file) =
                         archive.by_name(&filename.as_ref().to_string_lossy())
                     {
-                        let archive = iwd.clone();
-                        let handle_sync = false;
+                        if !iwd_is_pure(sp)
+                            && !sp.ignore_pure_check
+                            && !pure_ignore_files(&filename)
+                        {
+                            impure_iwd = Some(iwd_name.clone());
+                            continue;
+                        }
+                        if !files_are_loaded_globally(&filename) {
+                            add_iwd_pure_check_reference(sp);
+                        }
+
                         let file_size = zip_file.size();
+                        let mut data = Vec::with_capacity(file_size as _);
+                        if zip_file.read_to_end(&mut data).is_err() {
+                            continue;
+                        }
+
+                        let handle_sync = false;
                         let streamed = false;
                         let name = filename.as_ref().to_path_buf();
                         let fh = FileHandleData {
-                            file: Qfile::ZipFile { archive, name },
+                            file: Qfile::ZipFile {
+                                data: Cursor::new(data),
+                                name,
+                            },
                             handle_sync,
                             file_size: file_size as _,
                             streamed,
@@ -1231,15 +1805,11 @@ fn open_file_read_for_thread(
         );
     }
 
-    if impure_iwd {
+    if let Some(impure_iwd) = impure_iwd {
         com::errorln!(
             com::ErrorParm::DROP,
             "Impure client detected. Invalid .IWD files referenced!\n{}",
-            FSH.read().unwrap()[fd.as_usize()]
-                .as_ref()
-                .unwrap()
-                .name()
-                .display()
+            impure_iwd.display()
         );
     }
 
@@ -1342,13 +1912,14 @@ fn file_open_read(
 
 /// Opens [`filename`] in write mode.
 ///
-/// Creates the file it it does not exist.
+/// Creates the file it it does not exist, and truncates it if it does.
 fn file_open_write(
     filename: impl AsRef<Path>,
 ) -> std::io::Result<std::fs::File> {
     std::fs::File::options()
         .write(true)
         .create(true)
+        .truncate(true)
         .open(filename)
 }
 
@@ -1488,7 +2059,26 @@ pub fn read(fd: &Fd, buf: &mut [u8]) -> std::io::Result<usize> {
 /// Writes at most [`data.len()`] bytes.
 pub fn write(fd: &Fd, data: &[u8]) -> std::io::Result<usize> {
     let mut fsh = FSH.write().unwrap();
-    let fh = &mut fsh[fd.as_usize()].as_mut().unwrap();
+    write_to_handle(fsh[fd.as_usize()].as_mut().unwrap(), data)
+}
+
+/// Like [`write`], but fails with [`std::io::ErrorKind::WouldBlock`] instead
+/// of waiting if the file handles are locked.
+///
+/// Meant for writes that can happen while the current thread already has
+/// the file handles locked, like the log file, which gets written to from
+/// every [`com::print!`].
+pub fn try_write(fd: &Fd, data: &[u8]) -> std::io::Result<usize> {
+    let Ok(mut fsh) = FSH.try_write() else {
+        return Err(std::io::ErrorKind::WouldBlock.into());
+    };
+    write_to_handle(fsh[fd.as_usize()].as_mut().unwrap(), data)
+}
+
+fn write_to_handle(
+    fh: &mut FileHandleData,
+    data: &[u8],
+) -> std::io::Result<usize> {
     match fh.file {
         Qfile::ZipFile { .. } => {
             Err(std::io::ErrorKind::InvalidFilename.into())
@@ -1562,8 +2152,18 @@ pub fn read_file(
     }
     let (fd, file_size) = open_file_read_current_thread(filename)?;
     FS_LOADSTACK.increment_wrapping();
-    let mut buf = Vec::with_capacity(file_size as _);
-    read(&fd, &mut buf).map(|_| ReadFile(buf))
+    // Construct the ReadFile before reading so that FS_LOADSTACK is still
+    // decremented if the read fails
+    let mut buf = ReadFile(vec![0; file_size as _]);
+    let mut bytes_read = 0;
+    while bytes_read < buf.len() {
+        match read(&fd, &mut buf[bytes_read..])? {
+            0 => break,
+            n => bytes_read += n,
+        }
+    }
+    buf.truncate(bytes_read);
+    Ok(buf)
 }
 
 /// Writes [`data`] into the specified file.
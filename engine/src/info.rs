@@ -0,0 +1,132 @@
+#![allow(dead_code)]
+
+// This file implements info strings, the "\key\value\key\value" strings
+// used to send userinfo, serverinfo, and systeminfo between the client and
+// server. See `dvar::info_string` for building them from flagged Dvars.
+
+use crate::*;
+
+/// Maximum length of a normal info string (userinfo and serverinfo).
+pub const MAX_INFO_STRING: usize = 1024;
+/// Maximum length of a big info string (systeminfo).
+pub const BIG_INFO_STRING: usize = 8192;
+/// Maximum length of a single key.
+pub const MAX_INFO_KEY: usize = 1024;
+/// Maximum length of a single value.
+pub const MAX_INFO_VALUE: usize = 1024;
+
+/// Splits an info string into its key/value pairs, in order.
+///
+/// A trailing key without a value is paired with an empty value.
+pub fn parse(info: &str) -> Vec<(String, String)> {
+    let info = info.strip_prefix('\\').unwrap_or(info);
+    if info.is_empty() {
+        return Vec::new();
+    }
+
+    let mut parts = info.split('\\');
+    let mut pairs = Vec::new();
+    while let Some(key) = parts.next() {
+        let value = parts.next().unwrap_or_default();
+        pairs.push((key.to_owned(), value.to_owned()));
+    }
+
+    pairs
+}
+
+/// Returns the value for `key` in `info`, or [`None`] if `key` isn't set.
+pub fn value_for_key(info: &str, key: &str) -> Option<String> {
+    parse(info)
+        .into_iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(key))
+        .map(|(_, v)| v)
+}
+
+/// Removes `key` (and its value) from `info`, if present.
+pub fn remove_key(info: &mut String, key: &str) {
+    let pairs = parse(info);
+    if !pairs.iter().any(|(k, _)| k.eq_ignore_ascii_case(key)) {
+        return;
+    }
+
+    *info = pairs
+        .iter()
+        .filter(|(k, _)| !k.eq_ignore_ascii_case(key))
+        .map(|(k, v)| format!("\\{}\\{}", k, v))
+        .collect();
+}
+
+/// Checks that `s` can be embedded in an info string, i.e. that it doesn't
+/// contain any of the characters used as separators ('\\', ';', and '"').
+pub fn validate(s: &str) -> bool {
+    !s.contains(['\\', ';', '"'])
+}
+
+fn set_value_for_key_with_limit(
+    info: &mut String,
+    key: &str,
+    value: &str,
+    max_len: usize,
+) -> Result<(), ()> {
+    if key.len() >= MAX_INFO_KEY || value.len() >= MAX_INFO_VALUE {
+        com::println!(
+            console::Channel::ERROR,
+            "info::set_value_for_key: keys and values must be < {} characters",
+            MAX_INFO_KEY.min(MAX_INFO_VALUE)
+        );
+        return Err(());
+    }
+
+    if !validate(key) || !validate(value) {
+        com::println!(
+            console::Channel::ERROR,
+            "info::set_value_for_key: can't use keys or values with a \
+             \'\\\\\', \';\', or \'\"\': {} = {}",
+            key,
+            value
+        );
+        return Err(());
+    }
+
+    let mut new_info = info.clone();
+    remove_key(&mut new_info, key);
+    // Setting a key to an empty value just removes it
+    if !value.is_empty() {
+        new_info.push_str(&format!("\\{}\\{}", key, value));
+    }
+
+    if new_info.len() >= max_len {
+        com::println!(
+            console::Channel::ERROR,
+            "Info string length exceeded (setting \'{}\')",
+            key
+        );
+        return Err(());
+    }
+
+    *info = new_info;
+    Ok(())
+}
+
+/// Sets `key` to `value` in `info`, replacing any existing value.
+///
+/// Fails (leaving `info` unchanged) if `key` or `value` contains a separator
+/// character (see [`validate`]), or if the result would be
+/// [`MAX_INFO_STRING`] characters or longer.
+pub fn set_value_for_key(
+    info: &mut String,
+    key: &str,
+    value: &str,
+) -> Result<(), ()> {
+    set_value_for_key_with_limit(info, key, value, MAX_INFO_STRING)
+}
+
+/// Like [`set_value_for_key`], but for big info strings, which are limited
+/// to [`BIG_INFO_STRING`] characters instead.
+pub fn set_value_for_key_big(
+    info: &mut String,
+    key: &str,
+    value: &str,
+) -> Result<(), ()> {
+    set_value_for_key_with_limit(info, key, value, BIG_INFO_STRING)
+}
@@ -101,6 +101,7 @@ mod db;
 mod dvar;
 mod fs;
 mod gfx;
+mod info;
 mod input;
 mod key;
 mod locale;
@@ -110,6 +111,7 @@ mod platform;
 mod rb;
 mod render;
 mod seh;
+mod sv;
 mod sys;
 mod util;
 mod vid;
@@ -129,11 +131,11 @@ lazy_static! {
 pub fn run() {
     platform::os::target::main();
     platform::display_server::target::init();
-    let cmdline = sys::get_cmdline();
-    if cmdline.contains("autominidump") {
+    com::parse_command_line(&sys::get_cmdline());
+    if com::command_line_has("autominidump") {
         sys::start_minidump(false);
     } else {
-        if cmdline.contains("minidump") {
+        if com::command_line_has("minidump") {
             sys::start_minidump(true);
         } else {
             // Windows top-level exception handler bullshit
@@ -148,17 +150,15 @@ pub fn run() {
     locale::init();
 
     #[allow(clippy::collapsible_if)]
-    if cmdline.get(0..9).unwrap_or_default() != "allowdupe"
-        || cmdline.chars().nth(9).unwrap_or(' ') > ' '
-    {
-        if !cmdline.contains("g_connectpaths 3") {
+    if !com::command_line_has("allowdupe") {
+        if com::command_line_dvar("g_connectpaths").as_deref() != Some("3") {
             if sys::check_crash_or_rerun() == false {
                 return;
             }
         }
     }
 
-    if cmdline.contains("nosnd") {
+    if com::command_line_has("nosnd") {
         S_NOSND.store(true, Ordering::SeqCst);
     }
 
@@ -195,7 +195,7 @@ pub fn run() {
         }
         com::frame();
         // FUN_005cc940();
-        if dvar::get_bool("onlinegame").unwrap() {
+        if com::online_game() {
             // PbProcessServerEvents();
         }
     }
@@ -24,6 +24,7 @@ use windows::{
             Threading::{GetStartupInfoW, STARTUPINFOW},
         },
         UI::{
+            Controls::EM_SETSEL,
             Input::KeyboardAndMouse::{
                 MapVirtualKeyW, SetFocus, MAPVK_VSC_TO_VK_EX, VIRTUAL_KEY,
                 VK_ADD, VK_BACK, VK_CAPITAL, VK_CONTROL, VK_DECIMAL, VK_DELETE,
@@ -43,14 +44,15 @@ use windows::{
             WindowsAndMessaging::{
                 CallWindowProcA, DefWindowProcA, DestroyWindow,
                 GetSystemMetrics, GetWindowTextA, LoadCursorA, LoadIconA,
-                MessageBoxA, PostQuitMessage, RegisterClassExA, SetWindowPos,
-                SetWindowTextA, IDC_ARROW, MB_OK, MSG, SET_WINDOW_POS_FLAGS,
-                SM_REMOTESESSION, WA_INACTIVE, WM_ACTIVATE, WM_CHAR, WM_CLOSE,
-                WM_CREATE, WM_DESTROY, WM_DISPLAYCHANGE, WM_KEYDOWN, WM_KEYUP,
-                WM_KILLFOCUS, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN,
-                WM_MBUTTONUP, WM_MOVE, WM_RBUTTONDOWN, WM_RBUTTONUP,
-                WM_SETFOCUS, WM_SIZE, WM_SYSKEYDOWN, WM_SYSKEYUP,
-                WM_XBUTTONDOWN, WM_XBUTTONUP, WNDCLASSEXA,
+                MessageBoxA, PostQuitMessage, RegisterClassExA, SendMessageA,
+                SetWindowPos, SetWindowTextA, IDC_ARROW, MB_OK, MSG,
+                SET_WINDOW_POS_FLAGS, SM_REMOTESESSION, WA_INACTIVE,
+                WM_ACTIVATE, WM_CHAR, WM_CLOSE, WM_CREATE, WM_DESTROY,
+                WM_DISPLAYCHANGE, WM_KEYDOWN, WM_KEYUP, WM_KILLFOCUS,
+                WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN, WM_MBUTTONUP,
+                WM_MOVE, WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SETFOCUS, WM_SIZE,
+                WM_SYSKEYDOWN, WM_SYSKEYUP, WM_XBUTTONDOWN, WM_XBUTTONUP,
+                WNDCLASSEXA,
             },
         },
     },
@@ -59,6 +61,7 @@ use windows::{
 use libc::c_int;
 
 use crate::{
+    cmd,
     com::{self, ErrorParm},
     conbuf,
     platform::{display_server::target::WindowHandleExt, WindowHandle},
@@ -743,6 +746,20 @@ pub unsafe extern "system" fn input_line_wnd_proc(
                 );
                 sys::println!("]{}", text);
                 LRESULT(0)
+            } else if wparam.0 == VK_TAB.0 as usize {
+                let mut buf = [0u8; 1024];
+                let len = GetWindowTextA(hwnd, &mut buf);
+                let text = String::from_utf8_lossy(
+                    buf.get(..usize::try_from(len).unwrap_or(0))
+                        .unwrap_or_default(),
+                )
+                .to_string();
+                let completed = format!("{}\0", cmd::complete(&text));
+                SetWindowTextA(hwnd, PCSTR(completed.as_ptr()));
+                // Put the cursor at the end of the completed text
+                let end = completed.len() - 1;
+                SendMessageA(hwnd, EM_SETSEL, WPARAM(end), LPARAM(end as _));
+                LRESULT(0)
             } else {
                 CallWindowProcA(
                     conbuf::s_wcd().sys_input_line_wnd_proc,
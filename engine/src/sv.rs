@@ -0,0 +1,88 @@
+#![allow(dead_code)]
+
+use crate::*;
+
+use core::{
+    hash::{BuildHasher, Hasher},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::Duration,
+};
+use std::collections::hash_map::RandomState;
+
+// TODO - the server itself isn't implemented yet. For now, this only keeps
+// track of whether one is running, so that com has something to shut down
+// when it recovers from an error, and of the server time. It also owns the
+// server's Dvars and the IWD checksum feed the filesystem needs.
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+// Milliseconds the running server has been up for
+static TIME: AtomicU64 = AtomicU64::new(0);
+
+/// Checks whether a server is running.
+pub fn is_running() -> bool {
+    RUNNING.load(Ordering::SeqCst)
+}
+
+/// Returns how many milliseconds the running server has been up for.
+pub fn time() -> u64 {
+    TIME.load(Ordering::Relaxed)
+}
+
+/// Runs a server frame, `msec` after the last one. Does nothing if no server
+/// is running.
+pub fn frame(msec: Duration) {
+    if !is_running() {
+        return;
+    }
+
+    TIME.fetch_add(
+        u64::try_from(msec.as_millis()).unwrap_or(u64::MAX),
+        Ordering::Relaxed,
+    );
+}
+
+/// Shuts down the server, if one is running.
+///
+/// `reason` is printed, and will be sent to the server's clients once it
+/// has any.
+pub fn shutdown(reason: &str) {
+    if !RUNNING.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    TIME.store(0, Ordering::Relaxed);
+
+    com::println!(console::Channel::SERVER, "----- Server Shutdown -----");
+    com::println!(console::Channel::SERVER, "{}", reason);
+    com::println!(console::Channel::SERVER, "---------------------------");
+}
+
+fn register_dvars() {
+    // Clients get it along with the rest of the server's settings, which is
+    // how the filesystem knows whether to enforce the pure check
+    dvar::register_bool(
+        "sv_pure",
+        false,
+        dvar::DvarFlags::SYSTEM_INFO,
+        Some("Cannot use modified IWD files"),
+    )
+    .unwrap();
+}
+
+// Picks the value IWD pure checksums are seeded with. It only has to be
+// different every time, so that clients can't answer with checksums they
+// computed ahead of time
+#[allow(clippy::cast_possible_truncation)]
+fn checksum_feed() -> i32 {
+    RandomState::new().build_hasher().finish() as i32
+}
+
+/// Registers the server's Dvars and sets a new checksum feed for the
+/// filesystem (see [`fs::set_checksum_feed`]).
+///
+/// Has to run before the filesystem starts, so that the IWDs it loads are
+/// checksummed with the new feed.
+pub fn init() {
+    register_dvars();
+    fs::set_checksum_feed(checksum_feed());
+}
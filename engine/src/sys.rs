@@ -639,12 +639,21 @@ pub fn check_crash_or_rerun() -> bool {
     )
 }
 
+/// Returns the process's arguments (without the executable's name) as a
+/// single line, separated by spaces. Arguments containing whitespace are
+/// quoted so that they survive being tokenized again.
 pub fn get_cmdline() -> String {
-    let mut cmd_line = String::new();
-    std::env::args().for_each(|arg| {
-        cmd_line.push_str(&arg);
-    });
-    cmd_line.trim().to_owned()
+    std::env::args()
+        .skip(1)
+        .map(|arg| {
+            if arg.contains(char::is_whitespace) {
+                format!("\"{}\"", arg)
+            } else {
+                arg
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 pub fn start_minidump(b: bool) {
@@ -1,5 +1,7 @@
 #![allow(dead_code)]
 
+pub mod date;
+
 use core::f32::consts::PI;
 use std::{
     cell::{BorrowError, BorrowMutError, Ref, RefCell, RefMut},
@@ -0,0 +1,20 @@
+// Date conversions shared between the engine and build.rs (which includes
+// this file with #[path]), so this can't depend on anything else in the
+// crate.
+
+/// Converts `days` since the Unix epoch into a (year, month, day) civil
+/// date, using Howard Hinnant's `civil_from_days` (see "chrono-Compatible
+/// Low-Level Date Algorithms").
+#[allow(clippy::integer_division, clippy::arithmetic_side_effects)]
+pub fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}